@@ -0,0 +1,865 @@
+use {
+    clap::{
+        crate_description, crate_name, crate_version, value_t, value_t_or_exit, App, AppSettings,
+        Arg, ArgMatches, SubCommand,
+    },
+    hall_of_heros::{
+        client::{
+            decode_accent_color, decode_content_hash, encode_accent_color, encode_content_hash, get_gallery_authority_address,
+            get_graveyard_address, get_graveyard_page_address, get_history_address, get_metadata_address,
+            get_repository_address, Gallery, GalleryJson, Graveyard, History,
+        },
+        instruction,
+        processor::{
+            AddRecordArgs, BuyRecordV2Args, CreateGraveyardArgs, ModerateContentArgs, UpdateConfigArgs,
+            UpdateContentArgs, UpdatePriceArgs,
+        },
+        state::{ModerationStatus, NFTRecord, REPOSITORY_SIZE, REPO_ACCOUNT_SEED},
+    },
+    solana_client::rpc_client::RpcClient,
+    solana_program::program_pack::Pack,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        instruction::Instruction,
+        packet::PACKET_DATA_SIZE,
+        pubkey::Pubkey,
+        signature::{read_keypair_file, Keypair, Signer},
+        system_instruction,
+        transaction::Transaction,
+    },
+    spl_associated_token_account::get_associated_token_address,
+    spl_token::state::Account as TokenAccount,
+    std::{error::Error, fs, str::FromStr},
+};
+
+type CliResult = Result<(), Box<dyn Error>>;
+
+struct Config {
+    rpc_client: RpcClient,
+    program_id: Pubkey,
+    admin: Keypair,
+    repository: Pubkey,
+    dry_run: bool,
+}
+
+fn pubkey_arg(matches: &ArgMatches, name: &str) -> Result<Pubkey, Box<dyn Error>> {
+    let value = matches.value_of(name).ok_or(format!("missing {}", name))?;
+    Ok(Pubkey::from_str(value).map_err(|_| format!("invalid pubkey for {}: {}", name, value))?)
+}
+
+fn keypair_arg(matches: &ArgMatches, name: &str) -> Result<Keypair, Box<dyn Error>> {
+    let path = matches.value_of(name).ok_or(format!("missing {}", name))?;
+    read_keypair_file(path).map_err(|err| format!("failed to read keypair {}: {}", path, err).into())
+}
+
+// seat owner keypair if passed, admin overrides the owner otherwise
+fn owner_arg(matches: &ArgMatches) -> Result<Option<Keypair>, Box<dyn Error>> {
+    match matches.value_of("owner") {
+        Some(_) => Ok(Some(keypair_arg(matches, "owner")?)),
+        None => Ok(None),
+    }
+}
+
+fn owner_arg_def<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("owner")
+        .long("owner")
+        .value_name("KEYPAIR")
+        .takes_value(true)
+        .help("Seat owner keypair file, the admin keypair overrides the owner when omitted")
+}
+
+// token account currently holding the seat nft
+fn holder_token_account(config: &Config, mint: &Pubkey) -> Result<Pubkey, Box<dyn Error>> {
+    let holder = config
+        .rpc_client
+        .get_token_largest_accounts(mint)?
+        .into_iter()
+        .find(|balance| balance.amount.amount == "1")
+        .ok_or(format!("no token account holds {}", mint))?;
+    Ok(Pubkey::from_str(&holder.address)?)
+}
+
+fn content_hash_arg(matches: &ArgMatches) -> Result<Option<[u8; 32]>, Box<dyn Error>> {
+    Ok(matches.value_of("content_hash").map(decode_content_hash).transpose()?)
+}
+
+fn accent_color_arg(matches: &ArgMatches) -> Result<Option<[u8; 3]>, Box<dyn Error>> {
+    Ok(matches.value_of("accent_color").map(decode_accent_color).transpose()?)
+}
+
+// caption, link and accent color of a seat
+fn display_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("caption")
+            .long("caption")
+            .value_name("TEXT")
+            .takes_value(true),
+        Arg::with_name("link")
+            .long("link")
+            .value_name("URL")
+            .takes_value(true)
+            .help("External https link"),
+        Arg::with_name("accent_color")
+            .long("accent-color")
+            .value_name("#RRGGBB")
+            .takes_value(true),
+    ]
+}
+
+// fields of update-content and update-seat
+fn content_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    let mut args = vec![
+        Arg::with_name("content_uri")
+            .long("content-uri")
+            .value_name("URI")
+            .takes_value(true)
+            .help("Image of the hero"),
+        Arg::with_name("content_hash")
+            .long("content-hash")
+            .value_name("HEX")
+            .takes_value(true)
+            .help("SHA-256 of the asset at the content uri"),
+    ];
+    args.extend(display_args());
+    args.push(
+        Arg::with_name("sync_metadata")
+            .long("sync-metadata")
+            .help("Also point the NFT metadata uri at the content uri"),
+    );
+    args
+}
+
+fn hero_id_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("hero_id")
+        .long("hero-id")
+        .value_name("HERO_ID")
+        .takes_value(true)
+        .required(true)
+        .help("Seat index")
+}
+
+fn fetch_gallery(config: &Config) -> Result<Gallery, Box<dyn Error>> {
+    let data = config.rpc_client.get_account_data(&config.repository)?;
+    Ok(Gallery::unpack(&data)?)
+}
+
+fn print_record(nft_record: &NFTRecord) {
+    println!("Hero #{}", nft_record.hero_id);
+    println!("  key_nft:      {}", nft_record.key_nft);
+    println!("  content_uri:  {}", nft_record.content_uri);
+    println!("  last_price:   {}", nft_record.last_price);
+    println!("  listed_price: {}", nft_record.listed_price);
+    println!("  sale_count:   {}", nft_record.sale_count);
+    println!("  last_sold_at: {}", nft_record.last_sold_at);
+    println!("  moderation:   {:?}", nft_record.moderation);
+    if !nft_record.placeholder_uri.is_empty() {
+        println!("  placeholder:  {}", nft_record.placeholder_uri);
+    }
+    if let Some(content_hash) = &nft_record.content_hash {
+        println!("  content_hash: {}", encode_content_hash(content_hash));
+    }
+    println!("  caption:      {}", nft_record.caption);
+    println!("  link:         {}", nft_record.link);
+    if let Some(accent_color) = &nft_record.accent_color {
+        println!("  accent_color: {}", encode_accent_color(accent_color));
+    }
+}
+
+// sign and send the transaction, or print it when running dry
+fn process_transaction(
+    config: &Config,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &[&Keypair],
+) -> CliResult {
+    let (recent_blockhash, _fee_calculator) = config.rpc_client.get_recent_blockhash()?;
+    let transaction =
+        Transaction::new_signed_with_payer(instructions, Some(payer), signers, recent_blockhash);
+    if config.dry_run {
+        println!("{}", base64::encode(bincode::serialize(&transaction)?));
+    } else {
+        let signature = config
+            .rpc_client
+            .send_and_confirm_transaction(&transaction)?;
+        println!("Signature: {}", signature);
+    }
+    Ok(())
+}
+
+// create the repository account with the current layout
+fn create_repository_instruction(config: &Config) -> Result<Instruction, Box<dyn Error>> {
+    let admin = config.admin.pubkey();
+    Ok(system_instruction::create_account_with_seed(
+        &admin,
+        &config.repository,
+        &admin,
+        REPO_ACCOUNT_SEED,
+        config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(REPOSITORY_SIZE)?,
+        REPOSITORY_SIZE as u64,
+        &config.program_id,
+    ))
+}
+
+fn command_init(config: &Config, matches: &ArgMatches) -> CliResult {
+    let admin = config.admin.pubkey();
+    let ix = create_repository_instruction(config)?;
+    let history_ix = instruction::create_history(&config.program_id, &admin, &config.repository);
+    let graveyard_ix = instruction::create_graveyard(
+        &config.program_id,
+        &admin,
+        &config.repository,
+        CreateGraveyardArgs {
+            page_capacity: value_t!(matches, "graveyard_capacity", u16)?,
+        },
+    );
+    println!("Repository: {}", config.repository);
+    println!(
+        "Graveyard: {}",
+        get_graveyard_address(&config.repository, &config.program_id)
+    );
+    process_transaction(config, &[ix, history_ix, graveyard_ix], &admin, &[&config.admin])
+}
+
+fn command_migrate(config: &Config) -> CliResult {
+    let admin = config.admin.pubkey();
+    let repository_account = config
+        .rpc_client
+        .get_account_with_commitment(&config.repository, CommitmentConfig::confirmed())?
+        .value;
+    match repository_account {
+        Some(account) if account.data.len() >= REPOSITORY_SIZE => {
+            println!("Repository {} is already migrated", config.repository);
+            return Ok(());
+        }
+        // stash the seats and close the legacy repository
+        Some(_) => {
+            let ix = instruction::migrate_repository(&config.program_id, &admin, &config.repository, true);
+            process_transaction(config, &[ix], &admin, &[&config.admin])?;
+        }
+        // the seats were stashed by an earlier run
+        None => {}
+    }
+    let ix = instruction::migrate_repository(&config.program_id, &admin, &config.repository, false);
+    process_transaction(
+        config,
+        &[create_repository_instruction(config)?, ix],
+        &admin,
+        &[&config.admin],
+    )
+}
+
+fn command_add_seat(config: &Config, matches: &ArgMatches) -> CliResult {
+    let args = AddRecordArgs {
+        hero_id: value_t!(matches, "hero_id", u8)?,
+        content_uri: matches.value_of("content_uri").unwrap_or("").to_string(),
+        key_nft: pubkey_arg(matches, "mint")?,
+        last_price: value_t!(matches, "last_price", u64)?,
+        listed_price: value_t!(matches, "listed_price", u64)?,
+        force: matches.is_present("force"),
+        content_hash: content_hash_arg(matches)?,
+        caption: matches.value_of("caption").unwrap_or("").to_string(),
+        link: matches.value_of("link").unwrap_or("").to_string(),
+        accent_color: accent_color_arg(matches)?,
+    };
+    let admin = config.admin.pubkey();
+    let ix = instruction::add_record(&config.program_id, &admin, &config.repository, args);
+    process_transaction(config, &[ix], &admin, &[&config.admin])
+}
+
+// UpdatePrice and UpdateContent instructions of a seat, for the fields passed
+fn update_seat_instructions(
+    config: &Config,
+    matches: &ArgMatches,
+    setter: &Pubkey,
+) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    let hero_id = value_t!(matches, "hero_id", u8)?;
+    let gallery = fetch_gallery(config)?;
+    let nft_record = gallery
+        .seat(hero_id)
+        .ok_or(format!("seat {} is empty", hero_id))?;
+    let nft_token_account = holder_token_account(config, &nft_record.key_nft)?;
+    let mut instructions = Vec::new();
+    if matches.is_present("price") {
+        let args = UpdatePriceArgs {
+            hero_id,
+            key_nft: nft_record.key_nft,
+            new_price: value_t!(matches, "price", u64)?,
+        };
+        instructions.push(instruction::update_price(
+            &config.program_id,
+            setter,
+            &config.repository,
+            &nft_token_account,
+            args,
+        ));
+    }
+    let content_fields = ["content_uri", "content_hash", "caption", "link", "accent_color", "sync_metadata"];
+    if content_fields.iter().any(|field| matches.is_present(field)) {
+        // fields which aren't passed keep their current value
+        let args = UpdateContentArgs {
+            hero_id,
+            key_nft: nft_record.key_nft,
+            content_uri: matches.value_of("content_uri").map(|uri| uri.to_string()),
+            content_hash: content_hash_arg(matches)?.map(Some),
+            caption: matches.value_of("caption").map(|caption| caption.to_string()),
+            link: matches.value_of("link").map(|link| link.to_string()),
+            accent_color: accent_color_arg(matches)?.map(Some),
+        };
+        instructions.push(instruction::update_content(
+            &config.program_id,
+            setter,
+            &config.repository,
+            &nft_token_account,
+            matches.is_present("sync_metadata"),
+            args,
+        ));
+    }
+    Ok(instructions)
+}
+
+// update-price, update-content and update-seat, which sets both in one transaction
+fn command_update_seat(config: &Config, matches: &ArgMatches) -> CliResult {
+    let owner = owner_arg(matches)?;
+    let setter = owner.as_ref().unwrap_or(&config.admin);
+    let instructions = update_seat_instructions(config, matches, &setter.pubkey())?;
+    if instructions.is_empty() {
+        return Err("nothing to update, pass --price or a content field".into());
+    }
+    process_transaction(config, &instructions, &setter.pubkey(), &[setter])
+}
+
+fn command_delegate_metadata(config: &Config, matches: &ArgMatches) -> CliResult {
+    let hero_id = value_t!(matches, "hero_id", u8)?;
+    let gallery = fetch_gallery(config)?;
+    let nft_record = gallery
+        .seat(hero_id)
+        .ok_or(format!("seat {} is empty", hero_id))?;
+    let admin = config.admin.pubkey();
+    let gallery_authority = get_gallery_authority_address(&config.repository, &config.program_id);
+    let ix = spl_token_metadata::instruction::update_metadata_accounts(
+        spl_token_metadata::id(),
+        get_metadata_address(&nft_record.key_nft),
+        admin,
+        Some(gallery_authority),
+        None,
+        None,
+    );
+    println!("Gallery authority: {}", gallery_authority);
+    process_transaction(config, &[ix], &admin, &[&config.admin])
+}
+
+fn command_list(config: &Config) -> CliResult {
+    let gallery = fetch_gallery(config)?;
+    for (hero_id, seat) in gallery.seats.iter().enumerate() {
+        match seat {
+            Some(nft_record) => print_record(nft_record),
+            None => println!("Hero #{} (empty)", hero_id),
+        }
+    }
+    Ok(())
+}
+
+fn command_show(config: &Config, matches: &ArgMatches) -> CliResult {
+    let hero_id = value_t!(matches, "hero_id", u8)?;
+    let gallery = fetch_gallery(config)?;
+    match gallery.seat(hero_id) {
+        Some(nft_record) => print_record(nft_record),
+        None => println!("Hero #{} (empty)", hero_id),
+    }
+    let history_address = get_history_address(&config.repository, &config.program_id);
+    if let Ok(data) = config.rpc_client.get_account_data(&history_address) {
+        let history = History::unpack(&data)?;
+        for entry in history.seat(hero_id).map(|seat| seat.entries.as_slice()).unwrap_or(&[]) {
+            println!(
+                "  {:?} at slot {} ({}): {} -> {} for {}",
+                entry.kind, entry.slot, entry.timestamp, entry.seller, entry.buyer, entry.price
+            );
+        }
+    }
+    Ok(())
+}
+
+fn command_buy(config: &Config, matches: &ArgMatches) -> CliResult {
+    let hero_id = value_t!(matches, "hero_id", u8)?;
+    let buyer = keypair_arg(matches, "buyer")?;
+    let new_nft_mint = pubkey_arg(matches, "new_mint")?;
+    let old_nft_token_account = pubkey_arg(matches, "old_token_account")?;
+
+    let gallery = fetch_gallery(config)?;
+    let nft_record = gallery
+        .seat(hero_id)
+        .ok_or(format!("seat {} is empty", hero_id))?;
+    let old_token_account_data = config.rpc_client.get_account_data(&old_nft_token_account)?;
+    let prev_owner = TokenAccount::unpack(&old_token_account_data)?.owner;
+    let old_nft_metadata = get_metadata_address(&nft_record.key_nft);
+
+    let admin = config.admin.pubkey();
+    let nft_token_account_to_send = get_associated_token_address(&admin, &new_nft_mint);
+    let nft_token_account_to_receive = get_associated_token_address(&buyer.pubkey(), &new_nft_mint);
+    let create_receive_account = config
+        .rpc_client
+        .get_account_with_commitment(&nft_token_account_to_receive, CommitmentConfig::confirmed())?
+        .value
+        .is_none();
+    let graveyard = get_graveyard_address(&config.repository, &config.program_id);
+    let graveyard = Graveyard::unpack_index(&config.rpc_client.get_account_data(&graveyard)?)?;
+    let (page, _) = graveyard.locate(graveyard.buried);
+    let graveyard_page = get_graveyard_page_address(&config.repository, page, &config.program_id);
+
+    let ix = instruction::buy_record(
+        &config.program_id,
+        &admin,
+        &buyer.pubkey(),
+        &prev_owner,
+        &config.repository,
+        &nft_record.key_nft,
+        &old_nft_token_account,
+        &old_nft_metadata,
+        &new_nft_mint,
+        &nft_token_account_to_send,
+        &nft_token_account_to_receive,
+        &graveyard_page,
+        create_receive_account,
+        BuyRecordV2Args { hero_id },
+    );
+    println!("Price: {} lamports", nft_record.listed_price);
+    process_transaction(config, &[ix], &buyer.pubkey(), &[&config.admin, &buyer])
+}
+
+fn command_graveyard(config: &Config) -> CliResult {
+    let graveyard = get_graveyard_address(&config.repository, &config.program_id);
+    let data = config.rpc_client.get_account_data(&graveyard)?;
+    let index = Graveyard::unpack_index(&data)?;
+    let page_addresses: Vec<Pubkey> = (0..index.page_count())
+        .map(|page| get_graveyard_page_address(&config.repository, page, &config.program_id))
+        .collect();
+    let mut pages = Vec::with_capacity(page_addresses.len());
+    // rpc nodes return at most 100 accounts per request
+    for chunk in page_addresses.chunks(100) {
+        for (address, account) in chunk.iter().zip(config.rpc_client.get_multiple_accounts(chunk)?) {
+            pages.push(account.ok_or(format!("graveyard page {} not found", address))?.data);
+        }
+    }
+    let graveyard = Graveyard::unpack(&data, &pages)?;
+    println!("{} dead heros in {} pages", graveyard.index.buried, pages.len());
+    for dead_nft in graveyard.dead.iter() {
+        println!(
+            "  {} hero #{} owned by {} sold for {} at {}",
+            dead_nft.mint, dead_nft.hero_id, dead_nft.former_owner, dead_nft.sale_price, dead_nft.died_at
+        );
+    }
+    Ok(())
+}
+
+// sale proceeds of seats sold by the admin land in the admin wallet
+fn command_withdraw(config: &Config, matches: &ArgMatches) -> CliResult {
+    let admin = config.admin.pubkey();
+    let ix = system_instruction::transfer(
+        &admin,
+        &pubkey_arg(matches, "to")?,
+        value_t!(matches, "amount", u64)?,
+    );
+    process_transaction(config, &[ix], &admin, &[&config.admin])
+}
+
+fn command_moderate(config: &Config, matches: &ArgMatches) -> CliResult {
+    let hero_id = value_t!(matches, "hero_id", u8)?;
+    let gallery = fetch_gallery(config)?;
+    let nft_record = gallery
+        .seat(hero_id)
+        .ok_or(format!("seat {} is empty", hero_id))?;
+    let admin = config.admin.pubkey();
+    let ix = instruction::moderate_content(
+        &config.program_id,
+        &admin,
+        &config.repository,
+        if matches.is_present("sync_metadata") { Some(&nft_record.key_nft) } else { None },
+        ModerateContentArgs {
+            hero_id,
+            moderation: if matches.is_present("show") {
+                ModerationStatus::Visible
+            } else {
+                ModerationStatus::Hidden
+            },
+            placeholder_uri: matches.value_of("placeholder_uri").map(|uri| uri.to_string()),
+        },
+    );
+    process_transaction(config, &[ix], &admin, &[&config.admin])
+}
+
+fn command_set_config(config: &Config, matches: &ArgMatches) -> CliResult {
+    let admin = config.admin.pubkey();
+    let ix = instruction::update_config(
+        &config.program_id,
+        &admin,
+        &config.repository,
+        UpdateConfigArgs {
+            dead_name_template: value_t!(matches, "dead_name_template", String)?,
+            dead_uri_base: value_t!(matches, "dead_uri_base", String)?,
+            allowed_uri_prefixes: matches
+                .values_of("allowed_uri_prefix")
+                .map(|prefixes| prefixes.map(|prefix| prefix.to_string()).collect())
+                .unwrap_or_default(),
+            cooldown_seconds: value_t!(matches, "cooldown_seconds", u64)?,
+        },
+    );
+    process_transaction(config, &[ix], &admin, &[&config.admin])
+}
+
+fn command_export(config: &Config, matches: &ArgMatches) -> CliResult {
+    let gallery = fetch_gallery(config)?;
+    let json = gallery.to_json(&config.repository).to_string_pretty()?;
+    match matches.value_of("file") {
+        Some(path) => fs::write(path, json)?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn command_import(config: &Config, matches: &ArgMatches) -> CliResult {
+    let path = matches.value_of("file").ok_or("missing file")?;
+    let gallery_json = GalleryJson::parse(&fs::read_to_string(path)?)?;
+    let admin = config.admin.pubkey();
+    // every seat is validated before anything is sent
+    let config_ix = gallery_json.update_config_instruction(&config.program_id, &admin)?;
+    let transactions = gallery_json.import_transactions(
+        &config.program_id,
+        &admin,
+        matches.is_present("force"),
+        |instructions| {
+            let transaction = Transaction::new_with_payer(instructions, Some(&admin));
+            bincode::serialized_size(&transaction).map_or(false, |size| size as usize <= PACKET_DATA_SIZE)
+        },
+    )?;
+    if let Some(ix) = config_ix {
+        process_transaction(config, &[ix], &admin, &[&config.admin])?;
+    }
+    for instructions in transactions {
+        process_transaction(config, &instructions, &admin, &[&config.admin])?;
+    }
+    Ok(())
+}
+
+fn main() -> CliResult {
+    let default_keypair = format!(
+        "{}/.config/solana/id.json",
+        std::env::var("HOME").unwrap_or_else(|_| ".".to_string())
+    );
+    let matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("url")
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .default_value("http://127.0.0.1:8899")
+                .help("JSON RPC URL for the cluster"),
+        )
+        .arg(
+            Arg::with_name("program_id")
+                .long("program-id")
+                .value_name("PROGRAM_ID")
+                .takes_value(true)
+                .global(true)
+                .help("Hall of heros program id"),
+        )
+        .arg(
+            Arg::with_name("keypair")
+                .long("keypair")
+                .value_name("KEYPAIR")
+                .takes_value(true)
+                .global(true)
+                .default_value(&default_keypair)
+                .help("Admin keypair file"),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .global(true)
+                .help("Print the serialized transaction instead of sending it"),
+        )
+        .subcommand(
+            SubCommand::with_name("init")
+                .about("Create the repository, history and graveyard accounts")
+                .arg(
+                    Arg::with_name("graveyard_capacity")
+                        .long("graveyard-capacity")
+                        .value_name("COUNT")
+                        .takes_value(true)
+                        .default_value("100")
+                        .help("Number of dead heros per graveyard page"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("Move a repository created with 250 byte seats to the current layout"),
+        )
+        .subcommand(
+            SubCommand::with_name("add-seat")
+                .about("Add a hero to a seat")
+                .arg(hero_id_arg())
+                .arg(
+                    Arg::with_name("mint")
+                        .long("mint")
+                        .value_name("MINT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("NFT mint of the seat"),
+                )
+                .arg(
+                    Arg::with_name("content_uri")
+                        .long("content-uri")
+                        .value_name("URI")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("content_hash")
+                        .long("content-hash")
+                        .value_name("HEX")
+                        .takes_value(true)
+                        .help("SHA-256 of the asset at the content uri"),
+                )
+                .arg(
+                    Arg::with_name("last_price")
+                        .long("last-price")
+                        .value_name("LAMPORTS")
+                        .takes_value(true)
+                        .default_value("0"),
+                )
+                .arg(
+                    Arg::with_name("listed_price")
+                        .long("listed-price")
+                        .value_name("LAMPORTS")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help("Overwrite an occupied seat"),
+                )
+                .args(&display_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("update-price")
+                .about("Update listed price of a seat")
+                .arg(hero_id_arg())
+                .arg(owner_arg_def())
+                .arg(
+                    Arg::with_name("price")
+                        .long("price")
+                        .value_name("LAMPORTS")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("update-content")
+                .about("Update image, caption, link and accent color of a seat, omitted fields are kept")
+                .arg(hero_id_arg())
+                .arg(owner_arg_def())
+                .args(&content_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("update-seat")
+                .about("Update price and content of a seat in one transaction, omitted fields are kept")
+                .arg(hero_id_arg())
+                .arg(owner_arg_def())
+                .arg(
+                    Arg::with_name("price")
+                        .long("price")
+                        .value_name("LAMPORTS")
+                        .takes_value(true),
+                )
+                .args(&content_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("delegate-metadata")
+                .about("Hand update authority of a seat NFT metadata to the gallery")
+                .arg(hero_id_arg()),
+        )
+        .subcommand(SubCommand::with_name("list").about("List all seats"))
+        .subcommand(SubCommand::with_name("graveyard").about("List fallen heros"))
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Show a seat")
+                .arg(
+                    Arg::with_name("hero_id")
+                        .value_name("HERO_ID")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("buy")
+                .about("Buy a seat, signed by admin and buyer")
+                .arg(hero_id_arg())
+                .arg(
+                    Arg::with_name("buyer")
+                        .long("buyer")
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Buyer keypair file"),
+                )
+                .arg(
+                    Arg::with_name("new_mint")
+                        .long("new-mint")
+                        .value_name("MINT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("New NFT mint held by admin"),
+                )
+                .arg(
+                    Arg::with_name("old_token_account")
+                        .long("old-token-account")
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Previous owner's token account of the seat NFT"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("moderate")
+                .about("Hide a seat, or show it again with --show")
+                .arg(hero_id_arg())
+                .arg(
+                    Arg::with_name("show")
+                        .long("show")
+                        .conflicts_with("placeholder_uri")
+                        .help("Make a hidden seat visible again, showing its content"),
+                )
+                .arg(
+                    Arg::with_name("placeholder_uri")
+                        .long("placeholder-uri")
+                        .value_name("URI")
+                        .takes_value(true)
+                        .help("Shown instead of the content while hidden, the current one is kept when omitted"),
+                )
+                .arg(
+                    Arg::with_name("sync_metadata")
+                        .long("sync-metadata")
+                        .help("Also point the NFT metadata uri at the placeholder, or at the content with --show"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("set-config")
+                .about("Set how dead NFTs are named when a seat is sold")
+                .arg(
+                    Arg::with_name("dead_name_template")
+                        .long("dead-name-template")
+                        .value_name("TEMPLATE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Dead NFT name, {hero_id} and {sale_count} are replaced"),
+                )
+                .arg(
+                    Arg::with_name("dead_uri_base")
+                        .long("dead-uri-base")
+                        .value_name("URI")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Dead NFT uri is <URI><hero_id>-<sale_count>.json"),
+                )
+                .arg(
+                    Arg::with_name("allowed_uri_prefix")
+                        .long("allowed-uri-prefix")
+                        .value_name("PREFIX")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Content uris must start with one of these, https://, ar:// or ipfs:// when omitted"),
+                )
+                .arg(
+                    Arg::with_name("cooldown_seconds")
+                        .long("cooldown-seconds")
+                        .value_name("SECONDS")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("Seconds after a sale during which the seat can't be sold or repriced"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw")
+                .about("Move sale proceeds from the admin wallet to another wallet")
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .value_name("LAMPORTS")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Wallet receiving the lamports"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Dump the repository account to JSON")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .index(1)
+                        .help("Output file, stdout if omitted"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Add every seat of a JSON dump to the repository")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help("Overwrite occupied seats"),
+                ),
+        )
+        .get_matches();
+
+    let admin = keypair_arg(&matches, "keypair")?;
+    let program_id = pubkey_arg(&matches, "program_id")?;
+    let config = Config {
+        rpc_client: RpcClient::new_with_commitment(
+            value_t_or_exit!(matches, "url", String),
+            CommitmentConfig::confirmed(),
+        ),
+        repository: get_repository_address(&admin.pubkey(), &program_id)?,
+        program_id,
+        admin,
+        dry_run: matches.is_present("dry_run"),
+    };
+
+    match matches.subcommand() {
+        ("init", Some(sub_matches)) => command_init(&config, sub_matches),
+        ("migrate", Some(_)) => command_migrate(&config),
+        ("add-seat", Some(sub_matches)) => command_add_seat(&config, sub_matches),
+        ("update-price", Some(sub_matches))
+        | ("update-content", Some(sub_matches))
+        | ("update-seat", Some(sub_matches)) => command_update_seat(&config, sub_matches),
+        ("delegate-metadata", Some(sub_matches)) => command_delegate_metadata(&config, sub_matches),
+        ("list", Some(_)) => command_list(&config),
+        ("graveyard", Some(_)) => command_graveyard(&config),
+        ("show", Some(sub_matches)) => command_show(&config, sub_matches),
+        ("buy", Some(sub_matches)) => command_buy(&config, sub_matches),
+        ("moderate", Some(sub_matches)) => command_moderate(&config, sub_matches),
+        ("set-config", Some(sub_matches)) => command_set_config(&config, sub_matches),
+        ("withdraw", Some(sub_matches)) => command_withdraw(&config, sub_matches),
+        ("export", Some(sub_matches)) => command_export(&config, sub_matches),
+        ("import", Some(sub_matches)) => command_import(&config, sub_matches),
+        _ => unreachable!(),
+    }
+}
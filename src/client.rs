@@ -0,0 +1,491 @@
+use solana_program::{
+    instruction::Instruction,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use std::str::FromStr;
+
+use crate::{
+    error::HeroError,
+    instruction::{add_records, moderate_content, update_config},
+    processor::{AddRecordArgs, ModerateContentArgs, UpdateConfigArgs},
+    state::{
+        NFTRecord,
+        DeadNFT,
+        GalleryConfig,
+        ModerationStatus,
+        SeatHistory,
+        CONFIG_OFFSET,
+        GALLERY_CONFIG_SIZE,
+        DEAD_NFT_SIZE,
+        GraveyardIndex,
+        HISTORY_SIZE,
+        MAX_CAPTION_LENGTH,
+        MAX_CONTENT_URI_LENGTH,
+        MAX_LINK_LENGTH,
+        NFT_COUNT,
+        NFT_RECORD_SIZE,
+        REPO_ACCOUNT_SEED,
+        REPOSITORY_SIZE,
+        SEAT_HISTORY_SIZE,
+        find_gallery_authority_address,
+        find_graveyard_address,
+        find_graveyard_page_address,
+        find_history_address,
+        find_metadata_address
+    }
+};
+
+/// Number of signatures paid by a `BuyRecord` transaction: admin and buyer
+pub const BUY_RECORD_SIGNATURES: u64 = 2;
+
+/// Decoded repository account. Empty seats are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gallery {
+    pub seats: Vec<Option<NFTRecord>>,
+    /// `None` until `UpdateConfig` is run
+    pub config: Option<GalleryConfig>
+}
+
+/// Lamports a buyer pays for a seat
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    pub hero_id: u8,
+    /// listed price paid to the previous owner
+    pub price: u64,
+    /// transaction fees and rent of buyer's token account if it must be created
+    pub fees: u64,
+    pub total: u64
+}
+
+impl Gallery {
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.is_empty() {
+            return Err(HeroError::UninitializedRepository.into());
+        }
+        if data.len() < REPOSITORY_SIZE {
+            return Err(HeroError::RepositoryNotMigrated.into());
+        }
+        let seats = data
+            .chunks_exact(NFT_RECORD_SIZE)
+            .take(NFT_COUNT)
+            .map(|mut chunk| {
+                let nft_record = NFTRecord::deserialize(&mut chunk)?;
+                Ok(if nft_record.is_empty() { None } else { Some(nft_record) })
+            })
+            .collect::<Result<Vec<_>, ProgramError>>()?;
+        let config = match data.get(CONFIG_OFFSET..CONFIG_OFFSET + GALLERY_CONFIG_SIZE) {
+            Some(mut config_data) => Some(GalleryConfig::deserialize(&mut config_data)?)
+                .filter(|config| config.is_initialized),
+            None => None
+        };
+        Ok(Self { seats, config })
+    }
+
+    pub fn seat(&self, hero_id: u8) -> Option<&NFTRecord> {
+        self.seats.get(hero_id as usize).and_then(|seat| seat.as_ref())
+    }
+
+    /// Quote buying a seat. `receive_account_rent` is the rent of buyer's
+    /// associated token account when it doesn't exist yet. Fails if the total overflows.
+    /// A buyer starting a new graveyard page also pays its rent, which isn't quoted.
+    pub fn quote(
+        &self,
+        hero_id: u8,
+        lamports_per_signature: u64,
+        receive_account_rent: Option<u64>
+    ) -> Result<Quote, ProgramError> {
+        let nft_record = self.seat(hero_id).ok_or(HeroError::SeatEmpty)?;
+        let fees = BUY_RECORD_SIGNATURES
+            .checked_mul(lamports_per_signature)
+            .and_then(|signature_fees| signature_fees.checked_add(receive_account_rent.unwrap_or(0)))
+            .ok_or(ProgramError::InvalidArgument)?;
+        let total = nft_record.listed_price
+            .checked_add(fees)
+            .ok_or(ProgramError::InvalidArgument)?;
+        Ok(Quote {
+            hero_id,
+            price: nft_record.listed_price,
+            fees,
+            total
+        })
+    }
+}
+
+/// Decoded history account, the provenance of every seat
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+    pub seats: Vec<SeatHistory>
+}
+
+impl History {
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < HISTORY_SIZE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let seats = data
+            .chunks_exact(SEAT_HISTORY_SIZE)
+            .take(NFT_COUNT)
+            .map(SeatHistory::unpack)
+            .collect::<Result<Vec<_>, ProgramError>>()?;
+        Ok(Self { seats })
+    }
+
+    pub fn seat(&self, hero_id: u8) -> Option<&SeatHistory> {
+        self.seats.get(hero_id as usize)
+    }
+}
+
+/// Decoded graveyard account and its pages, every seat nft retired by a sale
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graveyard {
+    pub index: GraveyardIndex,
+    /// oldest first
+    pub dead: Vec<DeadNFT>
+}
+
+impl Graveyard {
+    /// Decode the graveyard account and its pages, `pages[i]` being the data of page `i`.
+    /// Pass at least `index.page_count()` pages.
+    pub fn unpack(data: &[u8], pages: &[Vec<u8>]) -> Result<Self, ProgramError> {
+        let index = Self::unpack_index(data)?;
+        let dead = (0..index.buried)
+            .map(|burial| {
+                let (page, slot) = index.locate(burial);
+                let page_data = pages.get(page as usize).ok_or(ProgramError::NotEnoughAccountKeys)?;
+                if page_data.len() != index.page_size() {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                let start = slot * DEAD_NFT_SIZE;
+                Ok(DeadNFT::deserialize(&mut &page_data[start..start + DEAD_NFT_SIZE])?)
+            })
+            .collect::<Result<Vec<_>, ProgramError>>()?;
+        Ok(Self { index, dead })
+    }
+
+    /// Decode the graveyard account alone, to find its pages
+    pub fn unpack_index(data: &[u8]) -> Result<GraveyardIndex, ProgramError> {
+        let index = GraveyardIndex::try_from_slice(data)?;
+        if index.page_capacity == 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(index)
+    }
+}
+
+/// Address of the graveyard account of `repository`
+pub fn get_graveyard_address(repository: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    find_graveyard_address(repository, program_id).0
+}
+
+/// Address of graveyard page `page` of `repository`
+pub fn get_graveyard_page_address(repository: &Pubkey, page: u32, program_id: &Pubkey) -> Pubkey {
+    find_graveyard_page_address(repository, page, program_id).0
+}
+
+/// Address of the history account of `repository`
+pub fn get_history_address(repository: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    find_history_address(repository, program_id).0
+}
+
+/// Address of the gallery authority of `repository`, update authority of synced nft metadata
+pub fn get_gallery_authority_address(repository: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    find_gallery_authority_address(repository, program_id).0
+}
+
+/// Address of the token metadata account of `mint`
+pub fn get_metadata_address(mint: &Pubkey) -> Pubkey {
+    find_metadata_address(mint).0
+}
+
+/// Address of the repository account owned by `admin`
+pub fn get_repository_address(admin: &Pubkey, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+    Ok(Pubkey::create_with_seed(admin, REPO_ACCOUNT_SEED, program_id)?)
+}
+
+/// Errors of a gallery JSON file
+#[derive(Error, Debug)]
+pub enum GalleryFileError {
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid pubkey: {0}")]
+    InvalidPubkey(String),
+    #[error("Hero {0} is out of range")]
+    HeroOverflow(u8),
+    #[error("Hero {0} appears more than once")]
+    DuplicateHeroId(u8),
+    #[error("NFT of hero {0} is already assigned to another hero")]
+    DuplicateNFTKey(u8),
+    #[error("Record of hero {0} does not fit in a seat")]
+    RecordTooLarge(u8),
+    #[error("Invalid content hash: {0}")]
+    InvalidContentHash(String),
+    #[error("Invalid accent color: {0}")]
+    InvalidAccentColor(String),
+    #[error("Content or placeholder uri of hero {0} is too long or not allowed by the gallery config")]
+    InvalidUri(u8),
+    #[error("Caption or link of hero {0} is too long, not printable or not https")]
+    InvalidContent(u8),
+}
+
+/// `#rrggbb` encoding of an accent color
+pub fn encode_accent_color(accent_color: &[u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", accent_color[0], accent_color[1], accent_color[2])
+}
+
+/// Parse an accent color, with or without leading `#`
+pub fn decode_accent_color(color: &str) -> Result<[u8; 3], GalleryFileError> {
+    let invalid = || GalleryFileError::InvalidAccentColor(color.to_string());
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut accent_color = [0; 3];
+    for (i, byte) in accent_color.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(accent_color)
+}
+
+/// Hex encoding of a content hash
+pub fn encode_content_hash(content_hash: &[u8; 32]) -> String {
+    content_hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Parse a hex encoded content hash
+pub fn decode_content_hash(hex: &str) -> Result<[u8; 32], GalleryFileError> {
+    let invalid = || GalleryFileError::InvalidContentHash(hex.to_string());
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut content_hash = [0; 32];
+    for (i, byte) in content_hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(content_hash)
+}
+
+/// JSON representation of a seat
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NFTRecordJson {
+    pub hero_id: u8,
+    pub content_uri: String,
+    pub key_nft: String,
+    pub last_price: u64,
+    pub listed_price: u64,
+    pub sale_count: u32,
+    #[serde(default)]
+    pub moderation: ModerationStatus,
+    /// hex encoded
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    #[serde(default)]
+    pub caption: String,
+    #[serde(default)]
+    pub link: String,
+    /// `#rrggbb`
+    #[serde(default)]
+    pub accent_color: Option<String>,
+    #[serde(default)]
+    pub last_sold_at: i64,
+    #[serde(default)]
+    pub placeholder_uri: String
+}
+
+/// JSON representation of a repository account. Only occupied seats are listed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GalleryJson {
+    pub repository: String,
+    pub nft_count: usize,
+    pub nft_record_size: usize,
+    pub config: Option<GalleryConfig>,
+    pub seats: Vec<NFTRecordJson>
+}
+
+impl From<&NFTRecord> for NFTRecordJson {
+    fn from(nft_record: &NFTRecord) -> Self {
+        Self {
+            hero_id: nft_record.hero_id,
+            content_uri: nft_record.content_uri.clone(),
+            key_nft: nft_record.key_nft.to_string(),
+            last_price: nft_record.last_price,
+            listed_price: nft_record.listed_price,
+            sale_count: nft_record.sale_count,
+            moderation: nft_record.moderation,
+            content_hash: nft_record.content_hash.as_ref().map(encode_content_hash),
+            caption: nft_record.caption.clone(),
+            link: nft_record.link.clone(),
+            accent_color: nft_record.accent_color.as_ref().map(encode_accent_color),
+            last_sold_at: nft_record.last_sold_at,
+            placeholder_uri: nft_record.placeholder_uri.clone()
+        }
+    }
+}
+
+impl NFTRecordJson {
+    pub fn to_record(&self) -> Result<NFTRecord, GalleryFileError> {
+        let key_nft = Pubkey::from_str(&self.key_nft)
+            .map_err(|_| GalleryFileError::InvalidPubkey(self.key_nft.clone()))?;
+        Ok(NFTRecord {
+            hero_id: self.hero_id,
+            content_uri: self.content_uri.clone(),
+            key_nft,
+            last_price: self.last_price,
+            listed_price: self.listed_price,
+            sale_count: self.sale_count,
+            moderation: self.moderation,
+            content_hash: self.content_hash.as_deref().map(decode_content_hash).transpose()?,
+            caption: self.caption.clone(),
+            link: self.link.clone(),
+            accent_color: self.accent_color.as_deref().map(decode_accent_color).transpose()?,
+            last_sold_at: self.last_sold_at,
+            placeholder_uri: self.placeholder_uri.clone()
+        })
+    }
+}
+
+impl Gallery {
+    pub fn to_json(&self, repository: &Pubkey) -> GalleryJson {
+        GalleryJson {
+            repository: repository.to_string(),
+            nft_count: NFT_COUNT,
+            nft_record_size: NFT_RECORD_SIZE,
+            config: self.config.clone(),
+            seats: self.seats.iter().flatten().map(NFTRecordJson::from).collect()
+        }
+    }
+}
+
+impl GalleryJson {
+    pub fn parse(json: &str) -> Result<Self, GalleryFileError> {
+        let gallery_json: Self = serde_json::from_str(json)?;
+        gallery_json.records()?;
+        Ok(gallery_json)
+    }
+
+    pub fn to_string_pretty(&self) -> Result<String, GalleryFileError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Validated records: unique hero_id in range and nft, fitting in a seat, and accepted by
+    /// `AddRecord` under the config of the file, or the default one when there is none
+    pub fn records(&self) -> Result<Vec<NFTRecord>, GalleryFileError> {
+        let config = self.config.clone().unwrap_or_default();
+        let mut seen = [false; NFT_COUNT];
+        let mut nft_records: Vec<NFTRecord> = Vec::with_capacity(self.seats.len());
+        for seat in self.seats.iter() {
+            let hero_id = seat.hero_id;
+            if hero_id as usize >= NFT_COUNT {
+                return Err(GalleryFileError::HeroOverflow(hero_id));
+            }
+            if seen[hero_id as usize] {
+                return Err(GalleryFileError::DuplicateHeroId(hero_id));
+            }
+            seen[hero_id as usize] = true;
+            let nft_record = seat.to_record()?;
+            if nft_records.iter().any(|other| other.key_nft == nft_record.key_nft) {
+                return Err(GalleryFileError::DuplicateNFTKey(hero_id));
+            }
+            let record_len = nft_record.try_to_vec().map(|data| data.len()).unwrap_or(usize::MAX);
+            if record_len > NFT_RECORD_SIZE {
+                return Err(GalleryFileError::RecordTooLarge(hero_id));
+            }
+            let allowed_uri = |uri: &str| uri.len() <= MAX_CONTENT_URI_LENGTH && config.is_allowed_uri(uri);
+            if !allowed_uri(&nft_record.content_uri) || !allowed_uri(&nft_record.placeholder_uri) {
+                return Err(GalleryFileError::InvalidUri(hero_id));
+            }
+            // `ModerateContent` can't hide a seat without placeholder
+            if nft_record.moderation == ModerationStatus::Hidden && nft_record.placeholder_uri.is_empty() {
+                return Err(GalleryFileError::InvalidUri(hero_id));
+            }
+            let printable = |text: &str, max_length: usize| {
+                text.len() <= max_length && !text.chars().any(char::is_control)
+            };
+            if !printable(&nft_record.caption, MAX_CAPTION_LENGTH)
+                || !printable(&nft_record.link, MAX_LINK_LENGTH)
+                || (!nft_record.link.is_empty() && !nft_record.link.starts_with("https://"))
+            {
+                return Err(GalleryFileError::InvalidContent(hero_id));
+            }
+            nft_records.push(nft_record);
+        }
+        Ok(nft_records)
+    }
+
+    /// `UpdateConfig` instruction restoring the config, if any
+    pub fn update_config_instruction(&self, program_id: &Pubkey, admin: &Pubkey) -> Result<Option<Instruction>, GalleryFileError> {
+        let repository = Pubkey::create_with_seed(admin, REPO_ACCOUNT_SEED, program_id)
+            .map_err(|_| GalleryFileError::InvalidPubkey(admin.to_string()))?;
+        Ok(self.config.as_ref().map(|config| {
+            update_config(program_id, admin, &repository, UpdateConfigArgs {
+                dead_name_template: config.dead_name_template.clone(),
+                dead_uri_base: config.dead_uri_base.clone(),
+                allowed_uri_prefixes: config.allowed_uri_prefixes.clone(),
+                cooldown_seconds: config.cooldown_seconds
+            })
+        }))
+    }
+
+    /// Transactions recreating this gallery in the repository of `admin`, each an `AddRecords`
+    /// instruction batching as many seats as `fits` accepts. Hidden seats are hidden again with
+    /// `ModerateContent` in the same transaction, behind the same placeholder. `sale_count` and
+    /// `last_sold_at` of each seat start over. Every seat is validated before any is returned.
+    pub fn import_transactions<F: Fn(&[Instruction]) -> bool>(
+        &self,
+        program_id: &Pubkey,
+        admin: &Pubkey,
+        force: bool,
+        fits: F
+    ) -> Result<Vec<Vec<Instruction>>, GalleryFileError> {
+        let repository = Pubkey::create_with_seed(admin, REPO_ACCOUNT_SEED, program_id)
+            .map_err(|_| GalleryFileError::InvalidPubkey(admin.to_string()))?;
+        let transaction = |batch: &[NFTRecord]| {
+            let mut instructions = vec![add_records(
+                program_id,
+                admin,
+                &repository,
+                batch.iter().map(|nft_record| AddRecordArgs {
+                    hero_id: nft_record.hero_id,
+                    content_uri: nft_record.content_uri.clone(),
+                    key_nft: nft_record.key_nft,
+                    last_price: nft_record.last_price,
+                    listed_price: nft_record.listed_price,
+                    force,
+                    content_hash: nft_record.content_hash,
+                    caption: nft_record.caption.clone(),
+                    link: nft_record.link.clone(),
+                    accent_color: nft_record.accent_color
+                }).collect()
+            )];
+            for nft_record in batch.iter().filter(|nft_record| nft_record.moderation != ModerationStatus::Visible) {
+                let placeholder_uri = Some(nft_record.placeholder_uri.clone()).filter(|uri| !uri.is_empty());
+                instructions.push(moderate_content(program_id, admin, &repository, None, ModerateContentArgs {
+                    hero_id: nft_record.hero_id,
+                    moderation: nft_record.moderation,
+                    placeholder_uri
+                }));
+            }
+            instructions
+        };
+
+        let mut transactions = vec![];
+        let mut batch: Vec<NFTRecord> = vec![];
+        for nft_record in self.records()? {
+            batch.push(nft_record);
+            // a seat too large to share a transaction is sent alone
+            if batch.len() > 1 && !fits(&transaction(&batch)) {
+                let nft_record = batch.pop().unwrap();
+                transactions.push(transaction(&batch));
+                batch = vec![nft_record];
+            }
+        }
+        if !batch.is_empty() {
+            transactions.push(transaction(&batch));
+        }
+        Ok(transactions)
+    }
+}
@@ -1,8 +1,14 @@
 use thiserror::Error;
 
-use solana_program::program_error::ProgramError;
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use solana_program::{
+    decode_error::DecodeError,
+    msg,
+    program_error::{PrintProgramError, ProgramError},
+};
 
-#[derive(Error, Debug, Copy, Clone)]
+#[derive(Error, Debug, Copy, Clone, FromPrimitive, PartialEq)]
 pub enum HeroError {
     /// Invalid instruction
     #[error("Invalid Instruction")]
@@ -16,6 +22,88 @@ pub enum HeroError {
     /// Not Rent Exempt
     #[error("Not Rent Exempt")]
     NotRentExempt,
+    /// Seat already holds a record
+    #[error("Seat occupied")]
+    SeatOccupied,
+    /// Mint is not a 0-decimal, supply-1 token
+    #[error("Invalid Mint")]
+    InvalidMint,
+    /// Mint already assigned to another seat
+    #[error("Duplicate NFT Key")]
+    DuplicateNFTKey,
+    /// Record stored in a seat belongs to another hero
+    #[error("Hero Id Mismatch")]
+    HeroIdMismatch,
+    /// Metadata account is not valid for the NFT
+    #[error("Invalid Metadata")]
+    InvalidMetadata,
+    /// Seat holds no record
+    #[error("Seat empty")]
+    SeatEmpty,
+    /// Repository account is not allocated for all seats
+    #[error("Uninitialized Repository")]
+    UninitializedRepository,
+    /// Token account is not owned by the expected wallet, holds another mint, or is frozen or delegated
+    #[error("Not NFT Owner")]
+    NotNFTOwner,
+    /// Token program account is not spl_token
+    #[error("Invalid Token Program")]
+    InvalidTokenProgram,
+    /// Token metadata program account is not spl_token_metadata
+    #[error("Invalid Token Metadata Program")]
+    InvalidTokenMetadataProgram,
+    /// System program account is not the system program
+    #[error("Invalid System Program")]
+    InvalidSystemProgram,
+    /// Mint or token account is not owned by the token program
+    #[error("Invalid Token Account Owner")]
+    InvalidTokenAccountOwner,
+    /// Token account holds another mint or belongs to another wallet
+    #[error("Invalid Token Account")]
+    InvalidTokenAccount,
+    /// Associated token account program account is not spl_associated_token_account
+    #[error("Invalid Associated Token Program")]
+    InvalidAssociatedTokenProgram,
+    /// Seat is listed above buyer's maximum price
+    #[error("Price Above Maximum")]
+    PriceAboveMaximum,
+    /// History account is not the one of the repository
+    #[error("Invalid History Account")]
+    InvalidHistoryAccount,
+    /// Graveyard account is not the one of the gallery
+    #[error("Invalid Graveyard Account")]
+    InvalidGraveyardAccount,
+    /// Instruction is no longer supported
+    #[error("Deprecated Instruction")]
+    DeprecatedInstruction,
+    /// Gallery config is not set
+    #[error("Uninitialized Config")]
+    UninitializedConfig,
+    /// Gallery config is not valid
+    #[error("Invalid Config")]
+    InvalidConfig,
+    /// Gallery authority account is not the expected program derived address,
+    /// or it isn't the update authority of nft metadata
+    #[error("Invalid Gallery Authority")]
+    InvalidGalleryAuthority,
+    /// Content uri doesn't match the allowed prefixes of gallery config or contains control characters
+    #[error("Invalid Uri")]
+    InvalidUri,
+    /// Display field of a seat is too long or contains control characters
+    #[error("Invalid Content")]
+    InvalidContent,
+    /// Seat was sold too recently to be sold or repriced
+    #[error("Seat Cooling Down")]
+    SeatCoolingDown,
+    /// Seat appears more than once in a batch
+    #[error("Duplicate Hero Id")]
+    DuplicateHeroId,
+    /// Migration account is not the one of the repository, or holds no seats to migrate
+    #[error("Invalid Migration Account")]
+    InvalidMigrationAccount,
+    /// Repository still has the legacy layout, run `MigrateRepository` first
+    #[error("Repository Not Migrated")]
+    RepositoryNotMigrated,
 }
 
 impl From<HeroError> for ProgramError {
@@ -23,3 +111,18 @@ impl From<HeroError> for ProgramError {
         ProgramError::Custom(e as u32)
     }
 }
+
+impl<T> DecodeError<T> for HeroError {
+    fn type_of() -> &'static str {
+        "HeroError"
+    }
+}
+
+impl PrintProgramError for HeroError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        msg!("Error: {}", self);
+    }
+}
@@ -1,16 +1,27 @@
 use solana_program::{
+    instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+    sysvar,
     msg
 };
 
-use borsh::{BorshDeserialize};
+use borsh::{BorshDeserialize, BorshSerialize};
 
 use crate::error::HeroError::InvalidInstruction;
+use crate::state::{
+    find_gallery_authority_address, find_graveyard_address, find_history_address, find_metadata_address,
+    find_migration_address
+};
 
 use crate::processor::{
-    AddRecordArgs, UpdateRecordArgs, BuyRecordArgs
+    AddRecordArgs, RemoveRecordArgs, UpdateRecordArgs, BuyRecordArgs, SwapSeatsArgs, TransferSeatArgs,
+    UpdateConfigArgs, BuyRecordV2Args, BuySeatV2Args, ModerateContentArgs, UpdateContentArgs,
+    UpdatePriceArgs, CreateGraveyardArgs
 };
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum HeroInstruction {
 
     /// Add Heros into Repository Account
@@ -19,22 +30,105 @@ pub enum HeroInstruction {
     ///
     /// 0. `[signer]` The account of the person adding the hero
     /// 1. `[writable]` Our repository account should be created prior to this instruction. It will hold all infos about our heros.
+    /// 2. `[]` The NFT mint of the seat
 
     AddRecord(AddRecordArgs),
 
-    /// Set Hero price
-    ///
+    /// Set Hero price and content_uri. Both are overwritten, prefer `UpdatePrice` or `UpdateContent`.
     ///
     /// Accounts expected:
     ///
-    /// 0. `[signer]` The account of the setter & signer
+    /// 0. `[signer]` The owner of the seat NFT, or the admin to override the owner
     /// 1. `[writable]` Our repository account which saves all onchain data
     /// 2. `[]` The NFT mint token account of which price will be changed
-    /// 3. `[]` The associated_token_account of nft mint token account
+    /// 3. `[]` The token account holding the NFT
+    /// 4. `[writable]` The NFT Metadata Account, only to sync content_uri into nft metadata
+    /// 5. `[]` Token Metadata Program Account, only to sync content_uri into nft metadata
+    /// 6. `[]` Gallery authority, update authority of the NFT metadata, only to sync content_uri
     
     UpdateRecord(UpdateRecordArgs),
 
-    /// Buy Hero
+    /// Deprecated, use `BuyRecordV2`. The buyer supplied the dead NFT name and uri.
+    
+    BuyRecord(BuyRecordArgs),
+
+    /// for test
+    OnChainMinting,
+
+    /// Remove Hero from Repository Account, leaving an empty seat
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The admin account
+    /// 1. `[writable]` Repository account
+
+    RemoveRecord(RemoveRecordArgs),
+
+    /// Add many Heros into Repository Account at once
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The admin account
+    /// 1. `[writable]` Repository account
+    /// 2..2+N. `[]` The NFT mint of each seat, in the order of records
+
+    AddRecords(Vec<AddRecordArgs>),
+
+    /// Swap seats between two owners
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Owner of seat a
+    /// 1. `[signer, writable]` Owner of seat b
+    /// 2. `[writable]` Repository account
+    /// 3. `[]` NFT mint of seat a
+    /// 4. `[]` Token account of owner a holding NFT of seat a
+    /// 5. `[]` NFT mint of seat b
+    /// 6. `[]` Token account of owner b holding NFT of seat b
+    /// 7. `[writable]` History account, provenance isn't recorded until `CreateHistory`
+    /// 8. `[]` System Program Account, only if there is a balancing payment
+
+    SwapSeats(SwapSeatsArgs),
+
+    /// Gift a seat to another wallet without a sale
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` Owner of the seat
+    /// 1. `[]` Recipient wallet
+    /// 2. `[writable]` Repository account
+    /// 3. `[]` NFT mint of the seat
+    /// 4. `[writable]` Owner's token account holding the NFT
+    /// 5. `[writable]` Recipient's token account to receive the NFT
+    /// 6. `[]` Token Program Account
+    /// 7. `[writable]` History account, provenance isn't recorded until `CreateHistory`
+    /// 8. `[]` System Program Account, only if account 5 doesn't exist yet
+    /// 9. `[]` Associated Token Program Account, only if account 5 doesn't exist yet
+    /// 10. `[]` Rent Sysvar, only if account 5 doesn't exist yet
+
+    TransferSeat(TransferSeatArgs),
+
+    /// Create the history account recording sales of every seat
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The admin account paying for history account
+    /// 1. `[]` Repository account
+    /// 2. `[writable]` History account, program derived from repository account
+    /// 3. `[]` System Program Account
+
+    CreateHistory,
+
+    /// Set gallery config, stored after the seats of Repository Account
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The admin account
+    /// 1. `[writable]` Repository account
+
+    UpdateConfig(UpdateConfigArgs),
+
+    /// Buy Hero. The dead NFT name and uri are generated from the gallery config.
     ///
     /// Accounts expected:
     ///
@@ -44,18 +138,113 @@ pub enum HeroInstruction {
     /// 3. `[writable]` Repository account
     /// 4. `[]` The Dead NFT Mint
     /// 5. `[]` The Dead NFT Token Account
-    /// 6. `[]` The Dead NFT Metadata Account
+    /// 6. `[writable]` The Dead NFT Metadata Account
     /// 7. `[]` New NFT mint
-    /// 8. `[]` The NFT token account from which send token
-    /// 9. `[]` The NFT token account to which receive token
-    /// 10. `[]` Token Program Account
-    /// 11. `[]` Token Metadata Program Account
-    /// 12. `[]` System Program Account
+    /// 8. `[writable]` The NFT token account from which send token
+    /// 9. `[writable]` The buyer's NFT token account to which receive token
+    /// 10. `[writable]` Graveyard page following the last burial, created by the buyer when it is a new page
+    /// 11. `[]` Token Program Account
+    /// 12. `[]` Token Metadata Program Account
+    /// 13. `[]` System Program Account
+    /// 14. `[writable]` History account, provenance isn't recorded until `CreateHistory`
+    /// 15. `[writable]` Graveyard account
+    /// 16. `[]` Gallery authority, signs for Dead NFT Metadata synced by `UpdateRecord`
+    /// 17. `[]` Associated Token Program Account, only if account 9 doesn't exist yet
+    /// 18. `[]` Rent Sysvar, only if account 9 doesn't exist yet
     
-    BuyRecord(BuyRecordArgs),
+    BuyRecordV2(BuyRecordV2Args),
 
-    /// for test
-    OnChainMinting
+    /// Buy many Heros at once. Reverts entirely if any seat fails.
+    /// The dead NFT names and uris are generated from the gallery config.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The admin account who has update authority of NFTs
+    /// 1. `[signer, writable]` The account of the person buys heros
+    /// 2. `[writable]` Repository account
+    /// 3. `[]` Token Program Account
+    /// 4. `[]` Token Metadata Program Account
+    /// 5. `[]` System Program Account
+    /// 6. `[]` Associated Token Program Account
+    /// 7. `[]` Rent Sysvar
+    /// 8. `[writable]` History account, provenance isn't recorded until `CreateHistory`
+    /// 9. `[writable]` Graveyard account
+    /// 10. `[]` Gallery authority, signs for Dead NFT Metadata synced by `UpdateRecord`
+    /// Then for each seat, in the order of args:
+    /// 0. `[writable]` Previous owner of nft
+    /// 1. `[]` The Dead NFT Mint
+    /// 2. `[]` The Dead NFT Token Account
+    /// 3. `[writable]` The Dead NFT Metadata Account
+    /// 4. `[]` New NFT mint
+    /// 5. `[writable]` The NFT token account from which send token
+    /// 6. `[writable]` The buyer's NFT token account to which receive token
+    /// 7. `[writable]` Graveyard page following the previous burial, created by the buyer when it is a new page
+
+    BuyRecordsV2(Vec<BuySeatV2Args>),
+
+    /// Hide a seat behind a placeholder uri, or show it again
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The admin account
+    /// 1. `[writable]` Repository account
+    /// 2. `[]` The NFT mint of the seat, only to sync content_uri into nft metadata
+    /// 3. `[writable]` The NFT Metadata Account, only to sync content_uri into nft metadata
+    /// 4. `[]` Token Metadata Program Account, only to sync content_uri into nft metadata
+    /// 5. `[]` Gallery authority, update authority of the NFT metadata, only to sync content_uri
+
+    ModerateContent(ModerateContentArgs),
+
+    /// Set image, caption, link or accent color of a Hero. Fields left unset and the price are untouched.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The owner of the seat NFT, or the admin to override the owner
+    /// 1. `[writable]` Our repository account which saves all onchain data
+    /// 2. `[]` The NFT mint token account of the seat
+    /// 3. `[]` The token account holding the NFT
+    /// 4. `[writable]` The NFT Metadata Account, only to sync content_uri into nft metadata
+    /// 5. `[]` Token Metadata Program Account, only to sync content_uri into nft metadata
+    /// 6. `[]` Gallery authority, update authority of the NFT metadata, only to sync content_uri
+
+    UpdateContent(UpdateContentArgs),
+
+    /// Set Hero price, content is left untouched
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The owner of the seat NFT, or the admin to override the owner
+    /// 1. `[writable]` Our repository account which saves all onchain data
+    /// 2. `[]` The NFT mint token account of the seat
+    /// 3. `[]` The token account holding the NFT
+
+    UpdatePrice(UpdatePriceArgs),
+
+    /// Create the graveyard account recording seat NFTs retired by sales
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The admin account paying for graveyard account
+    /// 1. `[]` Repository account
+    /// 2. `[writable]` Graveyard account, program derived from repository account. Dead NFTs are
+    ///    listed in its pages, program derived from repository account and page number
+    /// 3. `[]` System Program Account
+
+    CreateGraveyard(CreateGraveyardArgs),
+
+    /// Move a Repository Account with legacy 250 byte seats to the current layout, in two steps as
+    /// accounts can't be resized. On a legacy repository, its seats are copied to the migration account
+    /// and the repository is closed. Once the admin recreated it with `REPOSITORY_SIZE` bytes, the seats
+    /// are copied back and the migration account is closed.
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer, writable]` The admin account paying for migration account, receives lamports of closed accounts
+    /// 1. `[writable]` Repository account
+    /// 2. `[writable]` Migration account, program derived from repository account
+    /// 3. `[]` System Program Account, only on a legacy repository
+
+    MigrateRepository
 }
 
 impl HeroInstruction{
@@ -76,22 +265,599 @@ impl HeroInstruction{
                 Self::BuyRecord(Self::unpack_buy_record_args(rest)?)
             },
             3 => Self::OnChainMinting,
+            4 => {
+                Self::RemoveRecord(Self::unpack_remove_record_args(rest)?)
+            },
+            // 5 was Withdraw, don't reuse it
+            6 => {
+                Self::AddRecords(Self::unpack_add_records_args(rest)?)
+            },
+            // 7 is unused
+            8 => {
+                Self::SwapSeats(Self::unpack_swap_seats_args(rest)?)
+            },
+            9 => {
+                Self::TransferSeat(Self::unpack_transfer_seat_args(rest)?)
+            },
+            10 => Self::CreateHistory,
+            11 => {
+                Self::UpdateConfig(Self::unpack_update_config_args(rest)?)
+            },
+            12 => {
+                Self::BuyRecordV2(Self::unpack_buy_record_v2_args(rest)?)
+            },
+            13 => {
+                Self::BuyRecordsV2(Self::unpack_buy_records_v2_args(rest)?)
+            },
+            14 => {
+                Self::ModerateContent(Self::unpack_moderate_content_args(rest)?)
+            },
+            15 => {
+                Self::UpdateContent(Self::unpack_update_content_args(rest)?)
+            },
+            16 => {
+                Self::UpdatePrice(Self::unpack_update_price_args(rest)?)
+            },
+            17 => {
+                Self::CreateGraveyard(Self::unpack_create_graveyard_args(rest)?)
+            },
+            18 => Self::MigrateRepository,
             _ => return Err(InvalidInstruction.into()),
         })
     }
 
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        // writing into a Vec never fails
+        match self {
+            Self::AddRecord(args) => {
+                buf.push(0);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::UpdateRecord(args) => {
+                buf.push(1);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::BuyRecord(args) => {
+                buf.push(2);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::OnChainMinting => buf.push(3),
+            Self::RemoveRecord(args) => {
+                buf.push(4);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::AddRecords(args) => {
+                buf.push(6);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::SwapSeats(args) => {
+                buf.push(8);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::TransferSeat(args) => {
+                buf.push(9);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::CreateHistory => buf.push(10),
+            Self::UpdateConfig(args) => {
+                buf.push(11);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::BuyRecordV2(args) => {
+                buf.push(12);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::BuyRecordsV2(args) => {
+                buf.push(13);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::ModerateContent(args) => {
+                buf.push(14);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::UpdateContent(args) => {
+                buf.push(15);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::UpdatePrice(args) => {
+                buf.push(16);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::CreateGraveyard(args) => {
+                buf.push(17);
+                args.serialize(&mut buf).unwrap();
+            },
+            Self::MigrateRepository => buf.push(18),
+        }
+        buf
+    }
+
     fn unpack_add_record_args(input: &[u8]) -> Result<AddRecordArgs, ProgramError> {
-        let args = AddRecordArgs::try_from_slice(input)?;
+        let args = AddRecordArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_add_records_args(input: &[u8]) -> Result<Vec<AddRecordArgs>, ProgramError> {
+        let args = Vec::<AddRecordArgs>::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_swap_seats_args(input: &[u8]) -> Result<SwapSeatsArgs, ProgramError> {
+        let args = SwapSeatsArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_transfer_seat_args(input: &[u8]) -> Result<TransferSeatArgs, ProgramError> {
+        let args = TransferSeatArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_update_config_args(input: &[u8]) -> Result<UpdateConfigArgs, ProgramError> {
+        let args = UpdateConfigArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_buy_record_v2_args(input: &[u8]) -> Result<BuyRecordV2Args, ProgramError> {
+        let args = BuyRecordV2Args::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_buy_records_v2_args(input: &[u8]) -> Result<Vec<BuySeatV2Args>, ProgramError> {
+        let args = Vec::<BuySeatV2Args>::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_moderate_content_args(input: &[u8]) -> Result<ModerateContentArgs, ProgramError> {
+        let args = ModerateContentArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_update_content_args(input: &[u8]) -> Result<UpdateContentArgs, ProgramError> {
+        let args = UpdateContentArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_update_price_args(input: &[u8]) -> Result<UpdatePriceArgs, ProgramError> {
+        let args = UpdatePriceArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_create_graveyard_args(input: &[u8]) -> Result<CreateGraveyardArgs, ProgramError> {
+        let args = CreateGraveyardArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
+        Ok(args)
+    }
+
+    fn unpack_remove_record_args(input: &[u8]) -> Result<RemoveRecordArgs, ProgramError> {
+        let args = RemoveRecordArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
         Ok(args)
     }
 
     fn unpack_update_record_args(input: &[u8]) -> Result<UpdateRecordArgs, ProgramError> {
-        let args = UpdateRecordArgs::try_from_slice(input)?;
+        let args = UpdateRecordArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
         Ok(args)
     }
 
     fn unpack_buy_record_args(input: &[u8]) -> Result<BuyRecordArgs, ProgramError> {
-        let args = BuyRecordArgs::try_from_slice(input)?;
+        let args = BuyRecordArgs::try_from_slice(input).map_err(|_| InvalidInstruction)?;
         Ok(args)
     }
 }
+
+/// Creates an `AddRecord` instruction
+pub fn add_record(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    repository: &Pubkey,
+    args: AddRecordArgs,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new_readonly(args.key_nft, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::AddRecord(args).pack(),
+    }
+}
+
+/// Creates an `UpdateRecord` instruction.
+/// Pass `sync_metadata` to also point the nft metadata uri at `content_uri`,
+/// the gallery authority must then be update authority of the metadata.
+pub fn update_record(
+    program_id: &Pubkey,
+    setter: &Pubkey,
+    repository: &Pubkey,
+    nft_token_account: &Pubkey,
+    sync_metadata: bool,
+    args: UpdateRecordArgs,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*setter, true),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new_readonly(args.key_nft, false),
+        AccountMeta::new_readonly(*nft_token_account, false),
+    ];
+    if sync_metadata {
+        accounts.push(AccountMeta::new(find_metadata_address(&args.key_nft).0, false));
+        accounts.push(AccountMeta::new_readonly(spl_token_metadata::id(), false));
+        accounts.push(AccountMeta::new_readonly(find_gallery_authority_address(repository, program_id).0, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::UpdateRecord(args).pack(),
+    }
+}
+
+/// Creates a `BuyRecordV2` instruction.
+/// Pass `create_receive_account` when buyer's associated token account doesn't exist yet.
+/// `graveyard_page` follows the last burial, see `GraveyardIndex::locate`.
+#[allow(clippy::too_many_arguments)]
+pub fn buy_record(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    buyer: &Pubkey,
+    prev_owner: &Pubkey,
+    repository: &Pubkey,
+    old_nft_mint: &Pubkey,
+    old_nft_token_account: &Pubkey,
+    old_nft_metadata: &Pubkey,
+    new_nft_mint: &Pubkey,
+    nft_token_account_to_send: &Pubkey,
+    nft_token_account_to_receive: &Pubkey,
+    graveyard_page: &Pubkey,
+    create_receive_account: bool,
+    args: BuyRecordV2Args,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*buyer, true),
+        AccountMeta::new(*prev_owner, false),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new_readonly(*old_nft_mint, false),
+        AccountMeta::new_readonly(*old_nft_token_account, false),
+        AccountMeta::new(*old_nft_metadata, false),
+        AccountMeta::new_readonly(*new_nft_mint, false),
+        AccountMeta::new(*nft_token_account_to_send, false),
+        AccountMeta::new(*nft_token_account_to_receive, false),
+        AccountMeta::new(*graveyard_page, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_token_metadata::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(find_history_address(repository, program_id).0, false),
+        AccountMeta::new(find_graveyard_address(repository, program_id).0, false),
+        AccountMeta::new_readonly(find_gallery_authority_address(repository, program_id).0, false),
+    ];
+    if create_receive_account {
+        accounts.push(AccountMeta::new_readonly(spl_associated_token_account::id(), false));
+        accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::BuyRecordV2(args).pack(),
+    }
+}
+
+/// Creates a `RemoveRecord` instruction
+pub fn remove_record(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    repository: &Pubkey,
+    args: RemoveRecordArgs,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*repository, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::RemoveRecord(args).pack(),
+    }
+}
+
+/// Creates an `AddRecords` instruction
+pub fn add_records(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    repository: &Pubkey,
+    args: Vec<AddRecordArgs>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*repository, false),
+    ];
+    accounts.extend(args.iter().map(|record_args| AccountMeta::new_readonly(record_args.key_nft, false)));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::AddRecords(args).pack(),
+    }
+}
+
+/// Accounts of a single seat bought by a `BuyRecordsV2` instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BuySeatAccounts {
+    pub prev_owner: Pubkey,
+    pub old_nft_mint: Pubkey,
+    pub old_nft_token_account: Pubkey,
+    pub old_nft_metadata: Pubkey,
+    pub new_nft_mint: Pubkey,
+    pub nft_token_account_to_send: Pubkey,
+    pub nft_token_account_to_receive: Pubkey,
+    /// follows the burial of the previous seat, see `GraveyardIndex::locate`
+    pub graveyard_page: Pubkey,
+}
+
+/// Creates a `BuyRecordsV2` instruction
+pub fn buy_records(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    buyer: &Pubkey,
+    repository: &Pubkey,
+    seats: Vec<(BuySeatAccounts, BuySeatV2Args)>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*buyer, true),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_token_metadata::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new(find_history_address(repository, program_id).0, false),
+        AccountMeta::new(find_graveyard_address(repository, program_id).0, false),
+        AccountMeta::new_readonly(find_gallery_authority_address(repository, program_id).0, false),
+    ];
+    let mut args = Vec::with_capacity(seats.len());
+    for (seat_accounts, seat_args) in seats {
+        accounts.extend(vec![
+            AccountMeta::new(seat_accounts.prev_owner, false),
+            AccountMeta::new_readonly(seat_accounts.old_nft_mint, false),
+            AccountMeta::new_readonly(seat_accounts.old_nft_token_account, false),
+            AccountMeta::new(seat_accounts.old_nft_metadata, false),
+            AccountMeta::new_readonly(seat_accounts.new_nft_mint, false),
+            AccountMeta::new(seat_accounts.nft_token_account_to_send, false),
+            AccountMeta::new(seat_accounts.nft_token_account_to_receive, false),
+            AccountMeta::new(seat_accounts.graveyard_page, false),
+        ]);
+        args.push(seat_args);
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::BuyRecordsV2(args).pack(),
+    }
+}
+
+/// Creates a `SwapSeats` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn swap_seats(
+    program_id: &Pubkey,
+    owner_a: &Pubkey,
+    owner_b: &Pubkey,
+    repository: &Pubkey,
+    nft_mint_a: &Pubkey,
+    token_account_a: &Pubkey,
+    nft_mint_b: &Pubkey,
+    token_account_b: &Pubkey,
+    args: SwapSeatsArgs,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*owner_a, true),
+        AccountMeta::new(*owner_b, true),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new_readonly(*nft_mint_a, false),
+        AccountMeta::new_readonly(*token_account_a, false),
+        AccountMeta::new_readonly(*nft_mint_b, false),
+        AccountMeta::new_readonly(*token_account_b, false),
+        AccountMeta::new(find_history_address(repository, program_id).0, false),
+    ];
+    if args.lamports_a_to_b > 0 || args.lamports_b_to_a > 0 {
+        accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::SwapSeats(args).pack(),
+    }
+}
+
+/// Creates a `TransferSeat` instruction.
+/// Pass `create_recipient_account` when recipient's associated token account doesn't exist yet.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_seat(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    recipient: &Pubkey,
+    repository: &Pubkey,
+    nft_mint: &Pubkey,
+    owner_token_account: &Pubkey,
+    recipient_token_account: &Pubkey,
+    create_recipient_account: bool,
+    args: TransferSeatArgs,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new_readonly(*recipient, false),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new_readonly(*nft_mint, false),
+        AccountMeta::new(*owner_token_account, false),
+        AccountMeta::new(*recipient_token_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(find_history_address(repository, program_id).0, false),
+    ];
+    if create_recipient_account {
+        accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+        accounts.push(AccountMeta::new_readonly(spl_associated_token_account::id(), false));
+        accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::TransferSeat(args).pack(),
+    }
+}
+
+/// Creates a `CreateHistory` instruction
+pub fn create_history(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    repository: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(*repository, false),
+        AccountMeta::new(find_history_address(repository, program_id).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::CreateHistory.pack(),
+    }
+}
+
+/// Creates an `UpdateConfig` instruction
+pub fn update_config(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    repository: &Pubkey,
+    args: UpdateConfigArgs,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*repository, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::UpdateConfig(args).pack(),
+    }
+}
+
+/// Creates a `ModerateContent` instruction.
+/// Pass the seat `nft_mint` to also point its metadata uri at the placeholder, or back
+/// at the content when shown again. The gallery authority must then be update authority
+/// of the metadata.
+pub fn moderate_content(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    repository: &Pubkey,
+    nft_mint: Option<&Pubkey>,
+    args: ModerateContentArgs,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*repository, false),
+    ];
+    if let Some(nft_mint) = nft_mint {
+        accounts.push(AccountMeta::new_readonly(*nft_mint, false));
+        accounts.push(AccountMeta::new(find_metadata_address(nft_mint).0, false));
+        accounts.push(AccountMeta::new_readonly(spl_token_metadata::id(), false));
+        accounts.push(AccountMeta::new_readonly(find_gallery_authority_address(repository, program_id).0, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::ModerateContent(args).pack(),
+    }
+}
+
+/// Creates an `UpdateContent` instruction.
+/// Pass `sync_metadata` to also point the nft metadata uri at `content_uri`,
+/// the gallery authority must then be update authority of the metadata.
+pub fn update_content(
+    program_id: &Pubkey,
+    setter: &Pubkey,
+    repository: &Pubkey,
+    nft_token_account: &Pubkey,
+    sync_metadata: bool,
+    args: UpdateContentArgs,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*setter, true),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new_readonly(args.key_nft, false),
+        AccountMeta::new_readonly(*nft_token_account, false),
+    ];
+    if sync_metadata {
+        accounts.push(AccountMeta::new(find_metadata_address(&args.key_nft).0, false));
+        accounts.push(AccountMeta::new_readonly(spl_token_metadata::id(), false));
+        accounts.push(AccountMeta::new_readonly(find_gallery_authority_address(repository, program_id).0, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::UpdateContent(args).pack(),
+    }
+}
+
+/// Creates an `UpdatePrice` instruction
+pub fn update_price(
+    program_id: &Pubkey,
+    setter: &Pubkey,
+    repository: &Pubkey,
+    nft_token_account: &Pubkey,
+    args: UpdatePriceArgs,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*setter, true),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new_readonly(args.key_nft, false),
+        AccountMeta::new_readonly(*nft_token_account, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::UpdatePrice(args).pack(),
+    }
+}
+
+/// Creates a `CreateGraveyard` instruction
+pub fn create_graveyard(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    repository: &Pubkey,
+    args: CreateGraveyardArgs,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new_readonly(*repository, false),
+        AccountMeta::new(find_graveyard_address(repository, program_id).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::CreateGraveyard(args).pack(),
+    }
+}
+
+/// Creates a `MigrateRepository` instruction. Pass `legacy` for the first step, on the repository
+/// with legacy seats. The second step follows recreating the repository in the same transaction.
+pub fn migrate_repository(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    repository: &Pubkey,
+    legacy: bool,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new(*repository, false),
+        AccountMeta::new(find_migration_address(repository, program_id).0, false),
+    ];
+    if legacy {
+        accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: HeroInstruction::MigrateRepository.pack(),
+    }
+}
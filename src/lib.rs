@@ -3,5 +3,8 @@ pub mod instruction;
 pub mod processor;
 pub mod state;
 
+#[cfg(feature = "client")]
+pub mod client;
+
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
\ No newline at end of file
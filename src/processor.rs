@@ -5,15 +5,18 @@ use {
         msg,
         program_error::ProgramError,
         pubkey::Pubkey,
-        program::{invoke},
+        program::{invoke, invoke_signed},
         program_pack::Pack,
-        system_instruction  
+        system_instruction,
+        system_program,
+        sysvar::{clock::Clock, rent::Rent, Sysvar}
     },
     borsh::{BorshDeserialize, BorshSerialize},
     spl_token::state::{Account as TokenAccount, Mint},
+    spl_associated_token_account::{create_associated_token_account, get_associated_token_address},
     spl_token_metadata::{
         instruction::{ update_metadata_accounts },
-        state::{Metadata},
+        state::{Metadata, MAX_NAME_LENGTH, MAX_URI_LENGTH},
     },
 };
 
@@ -21,37 +24,178 @@ use crate::{
     error::HeroError, 
     instruction::HeroInstruction,
     state:: {
+        DeadNFT,
+        GalleryConfig,
+        LegacyNFTRecord,
+        ModerationStatus,
+        MAX_CAPTION_LENGTH,
+        MAX_CONTENT_URI_LENGTH,
+        MAX_LINK_LENGTH,
         NFTRecord,
+        ProvenanceEntry,
+        ProvenanceKind,
+        SeatHistory,
+        HISTORY_SEED,
+        HISTORY_SIZE,
+        SEAT_HISTORY_SIZE,
+        DEAD_NFT_SIZE,
+        CONFIG_OFFSET,
+        GALLERY_CONFIG_SIZE,
+        GRAVEYARD_SEED,
+        GRAVEYARD_SIZE,
+        MAX_GRAVEYARD_CAPACITY,
+        GALLERY_AUTHORITY_SEED,
+        GraveyardIndex,
+        find_gallery_authority_address,
+        find_graveyard_address,
+        find_graveyard_page_address,
+        find_history_address,
+        find_migration_address,
+        LEGACY_NFT_RECORD_SIZE,
+        MIGRATION_SEED,
+        NFT_COUNT,
         NFT_RECORD_SIZE,
+        REPOSITORY_SIZE,
         REPO_ACCOUNT_SEED
     }
 };
-use std::str::FromStr;
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct AddRecordArgs {
     pub hero_id: u8,
     pub content_uri: String,
-    pub key_nft: String,
+    pub key_nft: Pubkey,
     pub last_price: u64,
-    pub listed_price: u64
+    pub listed_price: u64,
+    /// overwrite the seat even if it is already occupied
+    pub force: bool,
+    pub content_hash: Option<[u8; 32]>,
+    pub caption: String,
+    pub link: String,
+    pub accent_color: Option<[u8; 3]>
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RemoveRecordArgs {
+    pub hero_id: u8
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct UpdateRecordArgs {
     pub hero_id: u8,
     pub key_nft: Pubkey,
     pub new_price: u64,
-    pub content_uri: String
+    pub content_uri: String,
+    /// hash of the asset at content_uri, cleared when `None`
+    pub content_hash: Option<[u8; 32]>
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct BuyRecordArgs {
     pub hero_id: u8,
     pub dead_uri: String,
     pub dead_name: String
 }
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BuyRecordV2Args {
+    pub hero_id: u8
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BuySeatV2Args {
+    pub hero_id: u8,
+    /// fail if seat is listed above this price
+    pub max_price: u64
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct UpdateConfigArgs {
+    pub dead_name_template: String,
+    pub dead_uri_base: String,
+    pub allowed_uri_prefixes: Vec<String>,
+    pub cooldown_seconds: u64
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct UpdatePriceArgs {
+    pub hero_id: u8,
+    pub key_nft: Pubkey,
+    pub new_price: u64
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+/// Display fields left `None` keep their current value
+pub struct UpdateContentArgs {
+    pub hero_id: u8,
+    pub key_nft: Pubkey,
+    /// image of the hero, a new one clears content_hash unless that is set too
+    pub content_uri: Option<String>,
+    pub content_hash: Option<Option<[u8; 32]>>,
+    pub caption: Option<String>,
+    pub link: Option<String>,
+    pub accent_color: Option<Option<[u8; 3]>>
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ModerateContentArgs {
+    pub hero_id: u8,
+    pub moderation: ModerationStatus,
+    /// shown instead of the content while hidden, the current one is kept when unset. Required to hide a seat without one
+    pub placeholder_uri: Option<String>
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SwapSeatsArgs {
+    pub hero_id_a: u8,
+    pub hero_id_b: u8,
+    /// balancing payment from owner of seat a to owner of seat b
+    pub lamports_a_to_b: u64,
+    /// balancing payment from owner of seat b to owner of seat a
+    pub lamports_b_to_a: u64
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct TransferSeatArgs {
+    pub hero_id: u8,
+    /// replace listed_price so the gift can't be bought at the previous owner's price
+    pub new_listed_price: Option<u64>
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct CreateGraveyardArgs {
+    /// number of dead nfts per graveyard page, at most `MAX_GRAVEYARD_CAPACITY`
+    pub page_capacity: u16
+}
+
+// accounts shared by every seat of a purchase
+#[derive(Clone, Copy)]
+struct BuyAccounts<'a, 'b> {
+    admin_account: &'a AccountInfo<'b>,
+    buyer_account: &'a AccountInfo<'b>,
+    repository_account: &'a AccountInfo<'b>,
+    token_program: &'a AccountInfo<'b>,
+    token_metadata_program: &'a AccountInfo<'b>,
+    system_program_account: &'a AccountInfo<'b>,
+    history_account: &'a AccountInfo<'b>,
+    graveyard_account: &'a AccountInfo<'b>,
+    gallery_authority: &'a AccountInfo<'b>,
+    associated_token_program: Option<&'a AccountInfo<'b>>,
+    rent_sysvar: Option<&'a AccountInfo<'b>>,
+}
+
+// accounts of a single seat being bought
+#[derive(Clone, Copy)]
+struct SeatAccounts<'a, 'b> {
+    prev_owner_account: &'a AccountInfo<'b>,
+    old_nft_mint: &'a AccountInfo<'b>,
+    old_nft_token_account: &'a AccountInfo<'b>,
+    old_nft_metadata_account: &'a AccountInfo<'b>,
+    new_nft_mint: &'a AccountInfo<'b>,
+    nft_token_account_to_send: &'a AccountInfo<'b>,
+    nft_token_account_to_receive: &'a AccountInfo<'b>,
+    graveyard_page: &'a AccountInfo<'b>,
+}
 
 pub struct Processor;
 impl Processor {
@@ -71,12 +215,65 @@ impl Processor {
                 msg!("Instruction: UpdateRecord");
                 Self::process_update_record(accounts, &args, program_id)
             },
-            HeroInstruction::BuyRecord(args) => {
-                msg!("Instruction: BuyRecord");
-                Self::process_buy_record(accounts, &args, program_id)
+            HeroInstruction::BuyRecord(_) => {
+                // dead nft name and uri were supplied by the buyer, use BuyRecordV2
+                msg!("Instruction: BuyRecord is deprecated");
+                Err(HeroError::DeprecatedInstruction.into())
             },
             HeroInstruction::OnChainMinting => {
                 Ok(())//Self::on_chain_minting(accounts, program_id)
+            },
+            HeroInstruction::RemoveRecord(args) => {
+                msg!("Instruction: RemoveRecord");
+                Self::process_remove_record(accounts, &args, program_id)
+            },
+            HeroInstruction::AddRecords(args) => {
+                msg!("Instruction: AddRecords");
+                Self::process_add_records(accounts, &args, program_id)
+            },
+            HeroInstruction::SwapSeats(args) => {
+                msg!("Instruction: SwapSeats");
+                Self::process_swap_seats(accounts, &args, program_id)
+            },
+            HeroInstruction::TransferSeat(args) => {
+                msg!("Instruction: TransferSeat");
+                Self::process_transfer_seat(accounts, &args, program_id)
+            },
+            HeroInstruction::CreateHistory => {
+                msg!("Instruction: CreateHistory");
+                Self::process_create_history(accounts, program_id)
+            },
+            HeroInstruction::UpdateConfig(args) => {
+                msg!("Instruction: UpdateConfig");
+                Self::process_update_config(accounts, &args, program_id)
+            },
+            HeroInstruction::BuyRecordV2(args) => {
+                msg!("Instruction: BuyRecordV2");
+                Self::process_buy_record(accounts, &args, program_id)
+            },
+            HeroInstruction::BuyRecordsV2(args) => {
+                msg!("Instruction: BuyRecordsV2");
+                Self::process_buy_records(accounts, &args, program_id)
+            },
+            HeroInstruction::ModerateContent(args) => {
+                msg!("Instruction: ModerateContent");
+                Self::process_moderate_content(accounts, &args, program_id)
+            },
+            HeroInstruction::UpdateContent(args) => {
+                msg!("Instruction: UpdateContent");
+                Self::process_update_content(accounts, &args, program_id)
+            },
+            HeroInstruction::UpdatePrice(args) => {
+                msg!("Instruction: UpdatePrice");
+                Self::process_update_price(accounts, &args, program_id)
+            },
+            HeroInstruction::CreateGraveyard(args) => {
+                msg!("Instruction: CreateGraveyard");
+                Self::process_create_graveyard(accounts, &args, program_id)
+            },
+            HeroInstruction::MigrateRepository => {
+                msg!("Instruction: MigrateRepository");
+                Self::process_migrate_repository(accounts, program_id)
             }
         }
     }
@@ -85,7 +282,9 @@ impl Processor {
     /// Add seats to our repository account. 
     /// 
     /// 1. verify authority of adder account
-    /// 2. add record to our repository
+    /// 2. verify nft mint is a real NFT and not assigned to another seat
+    /// 3. make sure we don't overwrite an occupied seat unless forced
+    /// 4. add record to our repository
     /// 
     fn process_add_record(
         accounts: &[AccountInfo],
@@ -114,238 +313,1826 @@ impl Processor {
             program_id
         )?;
 
-        // 2. save new nft record to our repository
-        let nft_record = NFTRecord {
-            hero_id: args.hero_id,
-            content_uri: args.content_uri.to_string(),
-            key_nft: Pubkey::from_str(&args.key_nft).unwrap(),
-            last_price: args.last_price,
-            listed_price: args.listed_price
-        };
+        // 2. & 3. verify nft mint and seat
+        let nft_mint_account = next_account_info(account_info_iter)?;
+        let nft_record = Self::validate_new_record(args, nft_mint_account, repository_account.clone())?;
+
+        // 4. save new nft record to our repository
         Self::save_nft_data_to_repository(&nft_record, repository_account.clone())?;
 
         Ok(())
     }
 
     /// 
-    /// users can change content_uri and price of hero
-    /// so we need to update record
+    /// Add many seats to our repository account at once.
     /// 
-    /// 1. verify setter authority. setter should be admin
-    /// 2. verify ownership of nft(seat)
-    /// 3. update record
+    /// 1. verify authority of adder account
+    /// 2. validate every seat, failing the whole batch on any invalid one
+    /// 3. add all records to our repository
     /// 
-    fn process_update_record(
+    fn process_add_records(
         accounts: &[AccountInfo],
-        args: &UpdateRecordArgs,
+        args: &[AddRecordArgs],
         program_id: &Pubkey
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
 
-        let setter_account = next_account_info(account_info_iter)?;
-        if !setter_account.is_signer {
+        let adder_account = next_account_info(account_info_iter)?;
+        if !adder_account.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
-        
+
         let repository_account = next_account_info(account_info_iter)?;
         if repository_account.owner != program_id {
             msg!("Derived account does not have the correct program id");
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        // verify setter authority. setter should be admin
+        // 1. verify adder authority. Adder should be admin
         Self::verify_admin_authority(
-            setter_account.key,
+            adder_account.key,
             repository_account.key,
             program_id
         )?;
-        
-        // nft token mint account
-        let nft_account = next_account_info(account_info_iter)?;
 
-        // verify ownership of nft with owner's associated token account
-        // associated token account of hero mint token address
-        let associated_token_account = next_account_info(account_info_iter)?;
-        let token_account_info = TokenAccount::unpack_from_slice(&associated_token_account.data.borrow())?;
-        if token_account_info.owner != *setter_account.key || token_account_info.mint != *nft_account.key {
-            msg!("NFT is not owned by signer.");
-            return Err(ProgramError::InvalidArgument);
+        // 2. validate all seats before writing any
+        let mut nft_records: Vec<NFTRecord> = Vec::with_capacity(args.len());
+        for record_args in args {
+            let nft_mint_account = next_account_info(account_info_iter)?;
+            let nft_record = Self::validate_new_record(record_args, nft_mint_account, repository_account.clone())?;
+            if nft_records.iter().any(|other| other.hero_id == nft_record.hero_id) {
+                msg!("Seat {} appears more than once in batch", nft_record.hero_id);
+                return Err(HeroError::DuplicateHeroId.into());
+            }
+            if nft_records.iter().any(|other| other.key_nft == nft_record.key_nft) {
+                msg!("NFT of seat {} appears more than once in batch", nft_record.hero_id);
+                return Err(HeroError::DuplicateNFTKey.into());
+            }
+            nft_records.push(nft_record);
         }
 
-        // get nft listed price from repository account
-        let mut nft_record = Self::get_nft_data_from_repository(
-            args.hero_id, 
-            nft_account.key,
-            repository_account.clone(),
-            nft_account.clone()
-        ).unwrap();
+        // 3. save all records
+        for nft_record in nft_records.iter() {
+            Self::save_nft_data_to_repository(nft_record, repository_account.clone())?;
+        }
+        msg!("Added {} seats", nft_records.len());
 
-        // update nft last price with listed_price
-        nft_record.listed_price = args.new_price;
-        nft_record.content_uri = args.content_uri.to_string();
-        Self::save_nft_data_to_repository(&nft_record, repository_account.clone())?;
+        Ok(())
+    }
+
+    // verify nft mint and seat of a new record
+    fn validate_new_record<'a>(
+        args: &AddRecordArgs,
+        nft_mint_account: &AccountInfo<'a>,
+        repository_account: AccountInfo<'a>,
+    ) -> Result<NFTRecord, ProgramError> {
+        // verify nft mint
+        if *nft_mint_account.key != args.key_nft {
+            msg!("NFT mint account does not match key_nft");
+            return Err(HeroError::InvalidNFTKey.into());
+        }
+        Self::verify_nft_mint(nft_mint_account)?;
+        Self::verify_nft_key_unique(args.hero_id, &args.key_nft, repository_account.clone())?;
+        Self::verify_content_uri(&args.content_uri, &repository_account)?;
+        Self::verify_display_fields(&args.caption, &args.link)?;
+
+        // refuse to clobber an occupied seat by accident
+        let current_record = Self::read_nft_record(args.hero_id, repository_account)?;
+        if !current_record.is_empty() && !args.force {
+            msg!("Seat {} is already occupied. Use force to overwrite.", args.hero_id);
+            return Err(HeroError::SeatOccupied.into());
+        }
+
+        Ok(NFTRecord {
+            hero_id: args.hero_id,
+            content_uri: args.content_uri.to_string(),
+            key_nft: args.key_nft,
+            last_price: args.last_price,
+            listed_price: args.listed_price,
+            sale_count: 0,
+            moderation: ModerationStatus::Visible,
+            content_hash: args.content_hash,
+            caption: args.caption.to_string(),
+            link: args.link.to_string(),
+            accent_color: args.accent_color,
+            last_sold_at: 0,
+            placeholder_uri: String::new()
+        })
+    }
+
+    /// 
+    /// Retire a seat from our repository account.
+    /// 
+    /// 1. verify authority of remover account
+    /// 2. zero the slot so that it reads as an empty seat
+    /// 
+    fn process_remove_record(
+        accounts: &[AccountInfo],
+        args: &RemoveRecordArgs,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let remover_account = next_account_info(account_info_iter)?;
+        if !remover_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. verify remover authority. Remover should be admin
+        Self::verify_admin_authority(
+            remover_account.key,
+            repository_account.key,
+            program_id
+        )?;
+
+        // 2. zero the slot
+        let (start, end) = Self::record_range(args.hero_id)?;
+        Self::verify_repository_size(end, repository_account)?;
+        for byte in repository_account.data.borrow_mut()[start..end].iter_mut() {
+            *byte = 0;
+        }
+        msg!("Seat {} removed", args.hero_id);
 
         Ok(())
     }
 
     /// 
-    /// users can buy seat to present their image
+    /// Curators hide a seat behind a placeholder uri, or show it again.
+    /// Content, ownership and prices are left untouched.
     /// 
     /// 1. verify admin authority
-    /// 2. verify ownership of nft(seat) - make sure prev_owner_account is owner of nft
-    /// 3. transfer nft from prev_owner to buyer
-    /// 4. update metadata of old nft
-    /// 5. update last_price of nft record
-    /// 6. transfer sol from buyer to prev_owner
+    /// 2. set moderation status and placeholder uri, showing the seat clears the placeholder
+    /// 3. point nft metadata uri at the displayed uri, if metadata accounts are passed
     /// 
-    fn process_buy_record(
+    fn process_moderate_content(
         accounts: &[AccountInfo],
-        args: &BuyRecordArgs,
+        args: &ModerateContentArgs,
         program_id: &Pubkey
     ) -> ProgramResult {
-        msg!("process_buy_record");
         let account_info_iter = &mut accounts.iter();
-        
-        let admin_account = next_account_info(account_info_iter)?;
 
-        let buyer_account = next_account_info(account_info_iter)?;
-        if !buyer_account.is_signer {
+        let admin_account = next_account_info(account_info_iter)?;
+        if !admin_account.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
-        let prev_owner_account = next_account_info(account_info_iter)?;
+
         let repository_account = next_account_info(account_info_iter)?;
         if repository_account.owner != program_id {
             msg!("Derived account does not have the correct program id");
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        // 1. verify admin authority.
+        // 1. verify admin authority
         Self::verify_admin_authority(
             admin_account.key,
             repository_account.key,
             program_id
         )?;
-        
-        // nft token mint account
-        let old_nft_mint = next_account_info(account_info_iter)?;
-
-        // prev_owner's associated token Account to send NFT
-        let old_nft_token_account = next_account_info(account_info_iter)?;
-        let old_nft_metadata_account = next_account_info(account_info_iter)?;
 
-        // 2. verify ownership of nft with prev_owner's associated token account
-        // associated token account of hero mint token address
-        let token_account_info = TokenAccount::unpack_from_slice(&old_nft_token_account.data.borrow())?;
-        if token_account_info.owner != *prev_owner_account.key || token_account_info.mint != *old_nft_mint.key {
-            msg!("Old NFT is not owned by prev_owner.");
-            return Err(ProgramError::InvalidArgument);
+        // 2. moderate the seat
+        let mut nft_record = Self::read_nft_record(args.hero_id, repository_account.clone())?;
+        if nft_record.is_empty() {
+            msg!("Seat {} is empty", args.hero_id);
+            return Err(HeroError::SeatEmpty.into());
+        }
+        nft_record.moderation = args.moderation;
+        match args.moderation {
+            ModerationStatus::Hidden => {
+                if let Some(placeholder_uri) = &args.placeholder_uri {
+                    Self::verify_content_uri(placeholder_uri, repository_account)?;
+                    nft_record.placeholder_uri = placeholder_uri.to_string();
+                }
+                // without a placeholder the content would still be shown, and synced into metadata
+                if nft_record.placeholder_uri.is_empty() {
+                    msg!("Seat {} has no placeholder to hide behind", args.hero_id);
+                    return Err(HeroError::InvalidConfig.into());
+                }
+            }
+            ModerationStatus::Visible => {
+                if args.placeholder_uri.is_some() {
+                    msg!("A visible seat shows its content, not a placeholder");
+                    return Err(HeroError::InvalidInstruction.into());
+                }
+                nft_record.placeholder_uri = String::new();
+            }
         }
+        Self::save_nft_data_to_repository(&nft_record, repository_account.clone())?;
+        msg!("Seat {} moderated: {:?}", args.hero_id, args.moderation);
 
-        // nft token mint account
-        let new_nft_mint = next_account_info(account_info_iter)?;
-        // admin's token Account tosend NFT
-        let nft_token_account_to_send = next_account_info(account_info_iter)?;
-        
-        // buyer's token Account to receive NFT
-        let nft_token_account_to_receive = next_account_info(account_info_iter)?;
+        // 3. keep nft metadata in lock-step with the hall
+        if let Ok(nft_account) = next_account_info(account_info_iter) {
+            let nft_metadata_account = next_account_info(account_info_iter)?;
+            let token_metadata_program = next_account_info(account_info_iter)?;
+            let gallery_authority = next_account_info(account_info_iter)?;
+            if nft_record.key_nft != *nft_account.key {
+                msg!("NFT mint doesn't match the seat");
+                return Err(HeroError::InvalidNFTKey.into());
+            }
+            Self::verify_program_account(
+                token_metadata_program,
+                &spl_token_metadata::id(),
+                HeroError::InvalidTokenMetadataProgram
+            )?;
+            Self::sync_metadata_uri(
+                nft_account,
+                nft_metadata_account,
+                gallery_authority,
+                token_metadata_program,
+                repository_account.key,
+                nft_record.display_uri(),
+                program_id
+            )?;
+        }
 
-        let token_program = next_account_info(account_info_iter)?;
+        Ok(())
+    }
 
-        // 3. transfer NFT from 'nft_token_account_to_send' to 'nft_token_account_to_receive'
-        msg!("before transfer instruction");
-        let transfer_ix = spl_token::instruction::transfer(
-            token_program.key,
-            nft_token_account_to_send.key,
-            nft_token_account_to_receive.key,
-            admin_account.key,
-            &[admin_account.key],
-            1
-        )?;
-        invoke(
-            &transfer_ix,
-            &[
-                nft_token_account_to_send.clone(),
-                nft_token_account_to_receive.clone(),
-                admin_account.clone(),
-                token_program.clone(),
-            ],
-        )?;
+    /// 
+    /// users can change content_uri and price of hero
+    /// so we need to update record. Prefer `process_update_price` and `process_update_content`
+    /// which don't overwrite the other fields.
+    /// 
+    /// 1. verify setter holds the nft(seat), or is admin overriding the owner
+    /// 2. read the seat
+    /// 3. update record
+    /// 4. point nft metadata uri at the new content_uri, or the placeholder while hidden,
+    ///    if metadata accounts are passed
+    /// 
+    fn process_update_record(
+        accounts: &[AccountInfo],
+        args: &UpdateRecordArgs,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
 
-        let token_metadata_program = next_account_info(account_info_iter)?;
+        let setter_account = next_account_info(account_info_iter)?;
+        if !setter_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
         
-        // 4. update metadata of dead nft
-        Self::update_metadata_old_nft(
-            admin_account.clone(),
-            old_nft_mint.clone(),
-            old_nft_metadata_account.clone(),
-            token_metadata_program.clone(),
-            &args
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. & 2. verify setter authority and ownership of nft
+        let nft_account = next_account_info(account_info_iter)?;
+        let associated_token_account = next_account_info(account_info_iter)?;
+        let mut nft_record = Self::verify_seat_setter(
+            setter_account,
+            repository_account,
+            nft_account,
+            associated_token_account,
+            args.hero_id,
+            &args.key_nft,
+            program_id
         )?;
 
-        // get nft listed price from repository account
-        let mut nft_record = Self::get_nft_data_from_repository(
-            args.hero_id, 
-            old_nft_mint.key,
-            repository_account.clone(),
-            old_nft_mint.clone()
-        ).unwrap();
+        // content_uri is always overwritten here, an empty one would wipe the hero
+        if args.content_uri.is_empty() {
+            msg!("content_uri can't be empty, use UpdatePrice to change the price alone");
+            return Err(HeroError::InvalidUri.into());
+        }
+        Self::verify_content_uri(&args.content_uri, repository_account)?;
+        if args.new_price != nft_record.listed_price {
+            let config = Self::read_gallery_config(repository_account)?;
+            Self::verify_cooldown(&nft_record, &config, Clock::get()?.unix_timestamp)?;
+        }
 
-        // 5. update nft last price with listed_price
-        nft_record.last_price = nft_record.listed_price;
-        // update nft key
-        nft_record.key_nft = *new_nft_mint.key;
+        // update nft last price with listed_price
+        nft_record.listed_price = args.new_price;
+        nft_record.content_uri = args.content_uri.to_string();
+        nft_record.content_hash = args.content_hash;
         Self::save_nft_data_to_repository(&nft_record, repository_account.clone())?;
 
-        msg!("before send sol. price={:?}", nft_record.listed_price);
-        let system_program_account = next_account_info(account_info_iter)?;
+        // 4. keep nft metadata in lock-step with the hall
+        if let Ok(nft_metadata_account) = next_account_info(account_info_iter) {
+            let token_metadata_program = next_account_info(account_info_iter)?;
+            let gallery_authority = next_account_info(account_info_iter)?;
+            Self::verify_program_account(
+                token_metadata_program,
+                &spl_token_metadata::id(),
+                HeroError::InvalidTokenMetadataProgram
+            )?;
+            Self::sync_metadata_uri(
+                nft_account,
+                nft_metadata_account,
+                gallery_authority,
+                token_metadata_program,
+                repository_account.key,
+                nft_record.display_uri(),
+                program_id
+            )?;
+        }
 
-        // 6. transfer sol from buyer to prev_owner
-        Self::sol_transfer(
-            buyer_account.clone(), 
-            prev_owner_account.clone(), 
-            system_program_account.clone(),
-            nft_record.listed_price
-        )?;
         Ok(())
     }
 
-    // transfer sol
-    fn sol_transfer<'a>(
-        source: AccountInfo<'a>,
-        destination: AccountInfo<'a>,
-        system_program: AccountInfo<'a>,
-        amount: u64,
-    ) -> Result<(), ProgramError> {
-        let ix = solana_program::system_instruction::transfer(source.key, destination.key, amount);
-        invoke(&ix, &[source, destination, system_program])
-    }
+    /// 
+    /// users can change the listed price of their hero without touching the content
+    /// 
+    /// 1. verify setter holds the nft(seat), or is admin overriding the owner
+    /// 2. read the seat
+    /// 3. update listed price once the seat is out of cooldown
+    /// 
+    fn process_update_price(
+        accounts: &[AccountInfo],
+        args: &UpdatePriceArgs,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
 
-    // fetch nft data from repository account with hero_id
-    fn get_nft_data_from_repository<'a>(
-        hero_id: u8,
-        key_nft: &Pubkey,
-        repository_account: AccountInfo<'a>,
-        nft_account: AccountInfo<'a>,
-    ) -> Result<NFTRecord, ProgramError> {
-        let start: usize = hero_id as usize * NFT_RECORD_SIZE;
-        let end: usize = start + NFT_RECORD_SIZE;
+        let setter_account = next_account_info(account_info_iter)?;
+        if !setter_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
 
-        let nft_record: NFTRecord = NFTRecord::deserialize(&mut &repository_account.data.borrow()[start..end])?;
-        
-        if nft_record.key_nft != *key_nft || nft_record.key_nft != *nft_account.key {
-            msg!("NFT Key dismatch.");
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. & 2. verify setter authority and ownership of nft
+        let nft_account = next_account_info(account_info_iter)?;
+        let associated_token_account = next_account_info(account_info_iter)?;
+        let mut nft_record = Self::verify_seat_setter(
+            setter_account,
+            repository_account,
+            nft_account,
+            associated_token_account,
+            args.hero_id,
+            &args.key_nft,
+            program_id
+        )?;
+
+        // 3. update listed price
+        let config = Self::read_gallery_config(repository_account)?;
+        Self::verify_cooldown(&nft_record, &config, Clock::get()?.unix_timestamp)?;
+        nft_record.listed_price = args.new_price;
+        Self::save_nft_data_to_repository(&nft_record, repository_account.clone())?;
+        msg!("Seat {} listed at {}", args.hero_id, args.new_price);
+
+        Ok(())
+    }
+
+    /// 
+    /// users can change how their hero is displayed without touching the price
+    /// 
+    /// 1. verify setter holds the nft(seat), or is admin overriding the owner
+    /// 2. read the seat
+    /// 3. update the display fields which are set and verify them
+    /// 4. point nft metadata uri at the new content_uri, or the placeholder while hidden,
+    ///    if metadata accounts are passed
+    /// 
+    fn process_update_content(
+        accounts: &[AccountInfo],
+        args: &UpdateContentArgs,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let setter_account = next_account_info(account_info_iter)?;
+        if !setter_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. & 2. verify setter authority and ownership of nft
+        let nft_account = next_account_info(account_info_iter)?;
+        let associated_token_account = next_account_info(account_info_iter)?;
+        let mut nft_record = Self::verify_seat_setter(
+            setter_account,
+            repository_account,
+            nft_account,
+            associated_token_account,
+            args.hero_id,
+            &args.key_nft,
+            program_id
+        )?;
+
+        // 3. update display fields
+        if let Some(content_uri) = &args.content_uri {
+            Self::verify_content_uri(content_uri, repository_account)?;
+            nft_record.content_uri = content_uri.to_string();
+            // the hash was of the replaced content
+            nft_record.content_hash = None;
+        }
+        if let Some(content_hash) = args.content_hash {
+            nft_record.content_hash = content_hash;
+        }
+        if let Some(caption) = &args.caption {
+            nft_record.caption = caption.to_string();
+        }
+        if let Some(link) = &args.link {
+            nft_record.link = link.to_string();
+        }
+        if let Some(accent_color) = args.accent_color {
+            nft_record.accent_color = accent_color;
+        }
+        Self::verify_display_fields(&nft_record.caption, &nft_record.link)?;
+        Self::save_nft_data_to_repository(&nft_record, repository_account.clone())?;
+
+        // 4. keep nft metadata in lock-step with the hall
+        if let Ok(nft_metadata_account) = next_account_info(account_info_iter) {
+            let token_metadata_program = next_account_info(account_info_iter)?;
+            let gallery_authority = next_account_info(account_info_iter)?;
+            Self::verify_program_account(
+                token_metadata_program,
+                &spl_token_metadata::id(),
+                HeroError::InvalidTokenMetadataProgram
+            )?;
+            Self::sync_metadata_uri(
+                nft_account,
+                nft_metadata_account,
+                gallery_authority,
+                token_metadata_program,
+                repository_account.key,
+                nft_record.display_uri(),
+                program_id
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// 
+    /// users can buy seat to present their image
+    /// 
+    /// 1. verify admin authority and program accounts
+    /// 2. buy the seat, see `buy_seat`
+    /// 
+    fn process_buy_record(
+        accounts: &[AccountInfo],
+        args: &BuyRecordV2Args,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        msg!("process_buy_record");
+        let account_info_iter = &mut accounts.iter();
+        
+        let admin_account = next_account_info(account_info_iter)?;
+        let buyer_account = next_account_info(account_info_iter)?;
+        let prev_owner_account = next_account_info(account_info_iter)?;
+        let repository_account = next_account_info(account_info_iter)?;
+
+        // nft token mint account
+        let old_nft_mint = next_account_info(account_info_iter)?;
+        // prev_owner's associated token Account
+        let old_nft_token_account = next_account_info(account_info_iter)?;
+        let old_nft_metadata_account = next_account_info(account_info_iter)?;
+
+        // nft token mint account
+        let new_nft_mint = next_account_info(account_info_iter)?;
+        // admin's token Account to send NFT
+        let nft_token_account_to_send = next_account_info(account_info_iter)?;
+        // buyer's token Account to receive NFT
+        let nft_token_account_to_receive = next_account_info(account_info_iter)?;
+        // graveyard page following the last burial
+        let graveyard_page = next_account_info(account_info_iter)?;
+
+        let buy_accounts = BuyAccounts {
+            admin_account,
+            buyer_account,
+            repository_account,
+            token_program: next_account_info(account_info_iter)?,
+            token_metadata_program: next_account_info(account_info_iter)?,
+            system_program_account: next_account_info(account_info_iter)?,
+            history_account: next_account_info(account_info_iter)?,
+            graveyard_account: next_account_info(account_info_iter)?,
+            gallery_authority: next_account_info(account_info_iter)?,
+            // only needed when buyer's token account doesn't exist yet
+            associated_token_program: next_account_info(account_info_iter).ok(),
+            rent_sysvar: next_account_info(account_info_iter).ok(),
+        };
+        let seat_accounts = SeatAccounts {
+            prev_owner_account,
+            old_nft_mint,
+            old_nft_token_account,
+            old_nft_metadata_account,
+            new_nft_mint,
+            nft_token_account_to_send,
+            nft_token_account_to_receive,
+            graveyard_page,
+        };
+
+        // 1. verify admin authority and program accounts
+        Self::verify_buy_accounts(&buy_accounts, program_id)?;
+
+        // 2. buy the seat
+        Self::buy_seat(&buy_accounts, &seat_accounts, args.hero_id, None, program_id)
+    }
+
+    /// 
+    /// users can buy many seats at once, reverting entirely if any seat fails
+    /// 
+    /// 1. verify admin authority and program accounts
+    /// 2. buy every seat, see `buy_seat`
+    /// 
+    fn process_buy_records(
+        accounts: &[AccountInfo],
+        args: &[BuySeatV2Args],
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let buy_accounts = BuyAccounts {
+            admin_account: next_account_info(account_info_iter)?,
+            buyer_account: next_account_info(account_info_iter)?,
+            repository_account: next_account_info(account_info_iter)?,
+            token_program: next_account_info(account_info_iter)?,
+            token_metadata_program: next_account_info(account_info_iter)?,
+            system_program_account: next_account_info(account_info_iter)?,
+            associated_token_program: Some(next_account_info(account_info_iter)?),
+            rent_sysvar: Some(next_account_info(account_info_iter)?),
+            history_account: next_account_info(account_info_iter)?,
+            graveyard_account: next_account_info(account_info_iter)?,
+            gallery_authority: next_account_info(account_info_iter)?,
+        };
+
+        // 1. verify admin authority and program accounts
+        Self::verify_buy_accounts(&buy_accounts, program_id)?;
+
+        // 2. buy every seat
+        for seat_args in args {
+            let seat_accounts = SeatAccounts {
+                prev_owner_account: next_account_info(account_info_iter)?,
+                old_nft_mint: next_account_info(account_info_iter)?,
+                old_nft_token_account: next_account_info(account_info_iter)?,
+                old_nft_metadata_account: next_account_info(account_info_iter)?,
+                new_nft_mint: next_account_info(account_info_iter)?,
+                nft_token_account_to_send: next_account_info(account_info_iter)?,
+                nft_token_account_to_receive: next_account_info(account_info_iter)?,
+                graveyard_page: next_account_info(account_info_iter)?,
+            };
+            Self::buy_seat(
+                &buy_accounts,
+                &seat_accounts,
+                seat_args.hero_id,
+                Some(seat_args.max_price),
+                program_id
+            )?;
+        }
+        msg!("Bought {} seats", args.len());
+
+        Ok(())
+    }
+
+    // verify accounts shared by every seat of a purchase
+    fn verify_buy_accounts(
+        buy_accounts: &BuyAccounts,
+        program_id: &Pubkey
+    ) -> Result<(), ProgramError> {
+        if !buy_accounts.buyer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if buy_accounts.repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Self::verify_program_account(buy_accounts.token_program, &spl_token::id(), HeroError::InvalidTokenProgram)?;
+        Self::verify_program_account(
+            buy_accounts.token_metadata_program,
+            &spl_token_metadata::id(),
+            HeroError::InvalidTokenMetadataProgram
+        )?;
+        Self::verify_program_account(
+            buy_accounts.system_program_account,
+            &system_program::id(),
+            HeroError::InvalidSystemProgram
+        )?;
+        Self::verify_history_account(
+            buy_accounts.history_account,
+            buy_accounts.repository_account.key,
+            program_id
+        )?;
+        Self::verify_graveyard_account(
+            buy_accounts.graveyard_account,
+            buy_accounts.repository_account.key,
+            program_id
+        )?;
+        Self::verify_gallery_authority(
+            buy_accounts.gallery_authority,
+            buy_accounts.repository_account.key,
+            program_id
+        )?;
+
+        Self::verify_admin_authority(
+            buy_accounts.admin_account.key,
+            buy_accounts.repository_account.key,
+            program_id
+        )
+    }
+
+    /// 
+    /// buy a single seat
+    /// 
+    /// 1. verify ownership of nft(seat) - make sure prev_owner_account is owner of nft
+    /// 2. verify seat isn't cooling down and listed price doesn't exceed buyer's maximum
+    /// 3. create buyer's associated token account if it doesn't exist yet
+    /// 4. transfer nft from admin to buyer and verify buyer received it
+    /// 5. update metadata of old nft with name and uri generated from gallery config
+    /// 6. update last_price and sale_count of nft record
+    /// 7. transfer sol from buyer to prev_owner
+    /// 8. record the sale in seat history
+    /// 9. bury old nft in the graveyard page following the last burial
+    /// 
+    fn buy_seat<'a, 'b>(
+        buy_accounts: &BuyAccounts<'a, 'b>,
+        seat_accounts: &SeatAccounts<'a, 'b>,
+        hero_id: u8,
+        max_price: Option<u64>,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let BuyAccounts {
+            admin_account,
+            buyer_account,
+            repository_account,
+            token_program,
+            token_metadata_program,
+            system_program_account,
+            history_account,
+            graveyard_account,
+            gallery_authority,
+            ..
+        } = *buy_accounts;
+        let SeatAccounts {
+            prev_owner_account,
+            old_nft_mint,
+            old_nft_token_account,
+            old_nft_metadata_account,
+            new_nft_mint,
+            nft_token_account_to_send,
+            nft_token_account_to_receive,
+            graveyard_page,
+        } = *seat_accounts;
+
+        // 1. verify ownership of nft with prev_owner's associated token account
+        // associated token account of hero mint token address. The old nft never leaves that account,
+        // it is only retired, so the account may be frozen or delegated.
+        Self::verify_token_program_owned(old_nft_mint)?;
+        if Self::unpack_nft_holding(old_nft_token_account, old_nft_mint.key)?.owner != *prev_owner_account.key {
+            msg!("NFT is not owned by {}.", prev_owner_account.key);
+            return Err(HeroError::NotNFTOwner.into());
+        }
+
+        // get nft listed price from repository account
+        let mut nft_record = Self::get_nft_data_from_repository(
+            hero_id, 
+            old_nft_mint.key,
+            repository_account.clone(),
+            old_nft_mint.clone()
+        )?;
+
+        let config = Self::read_gallery_config(repository_account)?;
+        if !config.is_initialized {
+            msg!("Gallery config is not set");
+            return Err(HeroError::UninitializedConfig.into());
+        }
+
+        // 2. verify cooldown and listed price
+        let now = Clock::get()?.unix_timestamp;
+        Self::verify_cooldown(&nft_record, &config, now)?;
+        if let Some(max_price) = max_price {
+            if nft_record.listed_price > max_price {
+                msg!("Seat {} is listed at {}, above maximum {}", hero_id, nft_record.listed_price, max_price);
+                return Err(HeroError::PriceAboveMaximum.into());
+            }
+        }
+
+        // admin should send the new nft
+        Self::verify_new_seat_nft(hero_id, new_nft_mint, nft_token_account_to_send, repository_account)?;
+
+        // 3. create buyer's associated token account if it doesn't exist yet
+        if nft_token_account_to_receive.data_is_empty() {
+            let associated_token_program = buy_accounts.associated_token_program
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            Self::verify_program_account(
+                associated_token_program,
+                &spl_associated_token_account::id(),
+                HeroError::InvalidAssociatedTokenProgram
+            )?;
+            let rent_sysvar = buy_accounts.rent_sysvar.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            Self::create_associated_token_account(
+                buyer_account.clone(),
+                buyer_account.clone(),
+                nft_token_account_to_receive.clone(),
+                new_nft_mint.clone(),
+                system_program_account.clone(),
+                token_program.clone(),
+                rent_sysvar.clone(),
+                associated_token_program.clone()
+            )?;
+        }
+
+        Self::verify_token_program_owned(nft_token_account_to_receive)?;
+        let receive_account_info = TokenAccount::unpack(&nft_token_account_to_receive.data.borrow())?;
+        if receive_account_info.owner != *buyer_account.key || receive_account_info.mint != *new_nft_mint.key {
+            msg!("Token account to receive is not buyer's account of new NFT.");
+            return Err(HeroError::InvalidTokenAccount.into());
+        }
+
+        // 4. transfer NFT from 'nft_token_account_to_send' to 'nft_token_account_to_receive'
+        msg!("before transfer instruction");
+        let transfer_ix = spl_token::instruction::transfer(
+            token_program.key,
+            nft_token_account_to_send.key,
+            nft_token_account_to_receive.key,
+            admin_account.key,
+            &[admin_account.key],
+            1
+        )?;
+        invoke(
+            &transfer_ix,
+            &[
+                nft_token_account_to_send.clone(),
+                nft_token_account_to_receive.clone(),
+                admin_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let receive_account_info = TokenAccount::unpack(&nft_token_account_to_receive.data.borrow())?;
+        if receive_account_info.amount != 1 {
+            msg!("Buyer should hold exactly one new NFT after transfer.");
+            return Err(HeroError::InvalidTokenAccount.into());
+        }
+
+        // 5. update metadata of dead nft
+        nft_record.sale_count = nft_record.sale_count
+            .checked_add(1)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Self::update_metadata_old_nft(
+            admin_account.clone(),
+            gallery_authority.clone(),
+            repository_account.key,
+            old_nft_mint.clone(),
+            old_nft_metadata_account.clone(),
+            token_metadata_program.clone(),
+            &config.dead_uri(hero_id, nft_record.sale_count),
+            &config.dead_name(hero_id, nft_record.sale_count),
+            program_id
+        )?;
+
+        // 6. update nft last price with listed_price
+        nft_record.last_price = nft_record.listed_price;
+        nft_record.last_sold_at = now;
+        // update nft key
+        nft_record.key_nft = *new_nft_mint.key;
+        Self::save_nft_data_to_repository(&nft_record, repository_account.clone())?;
+
+        msg!("before send sol. price={:?}", nft_record.listed_price);
+
+        // 7. transfer sol from buyer to prev_owner
+        Self::sol_transfer(
+            buyer_account.clone(), 
+            prev_owner_account.clone(), 
+            system_program_account.clone(),
+            nft_record.listed_price
+        )?;
+
+        // 8. record the sale
+        Self::record_provenance(
+            history_account,
+            hero_id,
+            ProvenanceKind::Sale,
+            prev_owner_account.key,
+            buyer_account.key,
+            nft_record.listed_price
+        )?;
+
+        // 9. bury old nft
+        Self::bury_nft(
+            graveyard_account,
+            graveyard_page,
+            buyer_account,
+            system_program_account,
+            repository_account.key,
+            DeadNFT {
+                mint: *old_nft_mint.key,
+                hero_id,
+                former_owner: *prev_owner_account.key,
+                sale_price: nft_record.listed_price,
+                died_at: now
+            },
+            program_id
+        )?;
+        Ok(())
+    }
+
+    /// 
+    /// Set gallery config stored after the seats of repository account
+    /// 
+    /// 1. verify admin authority
+    /// 2. verify generated dead nft names and uris fit in token metadata, and uri prefixes
+    /// 3. save config
+    /// 
+    fn process_update_config(
+        accounts: &[AccountInfo],
+        args: &UpdateConfigArgs,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let admin_account = next_account_info(account_info_iter)?;
+        if !admin_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. verify admin authority
+        Self::verify_admin_authority(
+            admin_account.key,
+            repository_account.key,
+            program_id
+        )?;
+
+        // 2. the longest generated values should still fit
+        let config = GalleryConfig {
+            is_initialized: true,
+            dead_name_template: args.dead_name_template.to_string(),
+            dead_uri_base: args.dead_uri_base.to_string(),
+            allowed_uri_prefixes: args.allowed_uri_prefixes.clone(),
+            cooldown_seconds: args.cooldown_seconds
+        };
+        let max_hero_id = (NFT_COUNT - 1) as u8;
+        if config.dead_name(max_hero_id, u32::MAX).len() > MAX_NAME_LENGTH
+            || config.dead_uri(max_hero_id, u32::MAX).len() > MAX_URI_LENGTH
+        {
+            msg!("Dead nft name or uri would be too long for token metadata");
+            return Err(HeroError::InvalidConfig.into());
+        }
+        // an empty prefix would allow any uri
+        if config.allowed_uri_prefixes.iter().any(|prefix| prefix.is_empty() || prefix.chars().any(char::is_control)) {
+            msg!("Allowed uri prefixes must be non empty and printable");
+            return Err(HeroError::InvalidConfig.into());
+        }
+
+        // 3. save config
+        Self::save_gallery_config(&config, repository_account)?;
+        msg!("Gallery config updated");
+
+        Ok(())
+    }
+
+    /// 
+    /// Create history account of the gallery
+    /// 
+    /// 1. verify admin authority
+    /// 2. create history account at its program derived address
+    /// 
+    fn process_create_history(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let admin_account = next_account_info(account_info_iter)?;
+        if !admin_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let history_account = next_account_info(account_info_iter)?;
+        let system_program_account = next_account_info(account_info_iter)?;
+        Self::verify_program_account(system_program_account, &system_program::id(), HeroError::InvalidSystemProgram)?;
+
+        // 1. verify admin authority
+        Self::verify_admin_authority(
+            admin_account.key,
+            repository_account.key,
+            program_id
+        )?;
+
+        // 2. create history account
+        let (history_address, bump_seed) = find_history_address(repository_account.key, program_id);
+        if history_address != *history_account.key {
+            msg!("History account should be {}", history_address);
+            return Err(HeroError::InvalidHistoryAccount.into());
+        }
+        if !history_account.data_is_empty() {
+            msg!("History account already exists");
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        Self::create_pda_account(
+            admin_account,
+            history_account,
+            system_program_account,
+            HISTORY_SIZE,
+            program_id,
+            &[HISTORY_SEED, repository_account.key.as_ref(), &[bump_seed]]
+        )?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Create graveyard account of the gallery
+    /// 
+    /// 1. verify admin authority and page capacity
+    /// 2. create graveyard account at its program derived address, its pages are created by sales
+    /// 
+    fn process_create_graveyard(
+        accounts: &[AccountInfo],
+        args: &CreateGraveyardArgs,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let admin_account = next_account_info(account_info_iter)?;
+        if !admin_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let graveyard_account = next_account_info(account_info_iter)?;
+        let system_program_account = next_account_info(account_info_iter)?;
+        Self::verify_program_account(system_program_account, &system_program::id(), HeroError::InvalidSystemProgram)?;
+
+        // 1. verify admin authority and page capacity
+        Self::verify_admin_authority(
+            admin_account.key,
+            repository_account.key,
+            program_id
+        )?;
+        let page_capacity = args.page_capacity as usize;
+        if page_capacity == 0 || page_capacity > MAX_GRAVEYARD_CAPACITY {
+            msg!("Graveyard page capacity should be between 1 and {}", MAX_GRAVEYARD_CAPACITY);
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // 2. create graveyard account
+        let (graveyard_address, bump_seed) = find_graveyard_address(repository_account.key, program_id);
+        if graveyard_address != *graveyard_account.key {
+            msg!("Graveyard account should be {}", graveyard_address);
+            return Err(HeroError::InvalidGraveyardAccount.into());
+        }
+        if !graveyard_account.data_is_empty() {
+            msg!("Graveyard account already exists");
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        Self::create_pda_account(
+            admin_account,
+            graveyard_account,
+            system_program_account,
+            GRAVEYARD_SIZE,
+            program_id,
+            &[GRAVEYARD_SEED, repository_account.key.as_ref(), &[bump_seed]]
+        )?;
+        let graveyard = GraveyardIndex {
+            buried: 0,
+            page_capacity: args.page_capacity
+        };
+        graveyard.serialize(&mut &mut graveyard_account.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Move a repository with legacy seats to the current layout. Accounts can't be resized,
+    /// so it takes two instructions:
+    /// 
+    /// 1. legacy repository: copy its seats in the current layout to the migration account and
+    ///    close the repository, the admin then recreates it with `REPOSITORY_SIZE` bytes
+    /// 2. recreated repository: copy the seats back and close the migration account
+    /// 
+    fn process_migrate_repository(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let admin_account = next_account_info(account_info_iter)?;
+        if !admin_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let migration_account = next_account_info(account_info_iter)?;
+
+        Self::verify_admin_authority(
+            admin_account.key,
+            repository_account.key,
+            program_id
+        )?;
+        let (migration_address, bump_seed) = find_migration_address(repository_account.key, program_id);
+        if migration_address != *migration_account.key {
+            msg!("Migration account should be {}", migration_address);
+            return Err(HeroError::InvalidMigrationAccount.into());
+        }
+
+        if repository_account.data_len() < REPOSITORY_SIZE {
+            // 1. stash the seats and close the legacy repository
+            let system_program_account = next_account_info(account_info_iter)?;
+            Self::verify_program_account(system_program_account, &system_program::id(), HeroError::InvalidSystemProgram)?;
+            if !migration_account.data_is_empty() {
+                msg!("Migration account already exists, recreate the repository and migrate again");
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            Self::create_pda_account(
+                admin_account,
+                migration_account,
+                system_program_account,
+                REPOSITORY_SIZE,
+                program_id,
+                &[MIGRATION_SEED, repository_account.key.as_ref(), &[bump_seed]]
+            )?;
+            Self::migrate_seats(
+                &repository_account.data.borrow(),
+                &mut migration_account.data.borrow_mut()
+            )?;
+            Self::close_account(repository_account, admin_account)?;
+            msg!("Seats stashed, recreate the repository with {} bytes and migrate again", REPOSITORY_SIZE);
+        } else {
+            // 2. restore the seats into the recreated repository
+            if migration_account.owner != program_id || migration_account.data_len() < REPOSITORY_SIZE {
+                msg!("Migration account holds no seats");
+                return Err(HeroError::InvalidMigrationAccount.into());
+            }
+            if repository_account.data.borrow().iter().any(|byte| *byte != 0) {
+                msg!("Repository account is already in use");
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            repository_account.data.borrow_mut()[..REPOSITORY_SIZE]
+                .copy_from_slice(&migration_account.data.borrow()[..REPOSITORY_SIZE]);
+            Self::close_account(migration_account, admin_account)?;
+            msg!("Repository migrated");
+        }
+
+        Ok(())
+    }
+
+    /// 
+    /// owner gifts seat to recipient without a sale
+    /// 
+    /// 1. verify ownership of nft(seat)
+    /// 2. create recipient's associated token account if it doesn't exist yet
+    /// 3. transfer nft from owner to recipient
+    /// 4. reset listed_price if asked so the gift can't be sniped, not while the seat is cooling down
+    /// 5. record the transfer in seat history
+    /// 
+    fn process_transfer_seat(
+        accounts: &[AccountInfo],
+        args: &TransferSeatArgs,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let owner_account = next_account_info(account_info_iter)?;
+        if !owner_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let recipient_account = next_account_info(account_info_iter)?;
+
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let nft_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let recipient_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        Self::verify_program_account(token_program, &spl_token::id(), HeroError::InvalidTokenProgram)?;
+        let history_account = next_account_info(account_info_iter)?;
+        Self::verify_history_account(history_account, repository_account.key, program_id)?;
+
+        // 1. verify ownership of nft with owner's token account
+        Self::verify_token_program_owned(nft_account)?;
+        Self::verify_nft_owner(owner_token_account, nft_account.key, owner_account.key)?;
+
+        let mut nft_record = Self::get_nft_data_from_repository(
+            args.hero_id,
+            nft_account.key,
+            repository_account.clone(),
+            nft_account.clone()
+        )?;
+
+        // 2. create recipient's associated token account if it doesn't exist yet
+        if recipient_token_account.data_is_empty() {
+            let system_program_account = next_account_info(account_info_iter)?;
+            Self::verify_program_account(system_program_account, &system_program::id(), HeroError::InvalidSystemProgram)?;
+            let associated_token_program = next_account_info(account_info_iter)?;
+            Self::verify_program_account(
+                associated_token_program,
+                &spl_associated_token_account::id(),
+                HeroError::InvalidAssociatedTokenProgram
+            )?;
+            let rent_sysvar = next_account_info(account_info_iter)?;
+            Self::create_associated_token_account(
+                owner_account.clone(),
+                recipient_account.clone(),
+                recipient_token_account.clone(),
+                nft_account.clone(),
+                system_program_account.clone(),
+                token_program.clone(),
+                rent_sysvar.clone(),
+                associated_token_program.clone()
+            )?;
+        }
+
+        Self::verify_token_program_owned(recipient_token_account)?;
+        let recipient_account_info = TokenAccount::unpack(&recipient_token_account.data.borrow())?;
+        if recipient_account_info.owner != *recipient_account.key || recipient_account_info.mint != *nft_account.key {
+            msg!("Token account to receive is not recipient's account of NFT.");
+            return Err(HeroError::InvalidTokenAccount.into());
+        }
+
+        // 3. transfer nft to recipient
+        let transfer_ix = spl_token::instruction::transfer(
+            token_program.key,
+            owner_token_account.key,
+            recipient_token_account.key,
+            owner_account.key,
+            &[owner_account.key],
+            1
+        )?;
+        invoke(
+            &transfer_ix,
+            &[
+                owner_token_account.clone(),
+                recipient_token_account.clone(),
+                owner_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        // 4. reset listed price
+        if let Some(listed_price) = args.new_listed_price {
+            if listed_price != nft_record.listed_price {
+                let config = Self::read_gallery_config(repository_account)?;
+                Self::verify_cooldown(&nft_record, &config, Clock::get()?.unix_timestamp)?;
+            }
+            nft_record.listed_price = listed_price;
+            Self::save_nft_data_to_repository(&nft_record, repository_account.clone())?;
+        }
+
+        // 5. record the transfer
+        Self::record_provenance(
+            history_account,
+            args.hero_id,
+            ProvenanceKind::Transfer,
+            owner_account.key,
+            recipient_account.key,
+            0
+        )?;
+        msg!("Seat {} transferred from {} to {}", args.hero_id, owner_account.key, recipient_account.key);
+
+        Ok(())
+    }
+
+    /// 
+    /// two owners trade their seats
+    /// 
+    /// 1. verify both owners hold the nft of their seat and neither seat is cooling down
+    /// 2. exchange key_nft, content and display fields with their moderation, and listed_price of the records
+    /// 3. transfer balancing payment between owners
+    /// 4. record the swap in history of both seats
+    /// 
+    fn process_swap_seats(
+        accounts: &[AccountInfo],
+        args: &SwapSeatsArgs,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        let owner_account_a = next_account_info(account_info_iter)?;
+        let owner_account_b = next_account_info(account_info_iter)?;
+        if !owner_account_a.is_signer || !owner_account_b.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let repository_account = next_account_info(account_info_iter)?;
+        if repository_account.owner != program_id {
+            msg!("Derived account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if args.hero_id_a == args.hero_id_b {
+            msg!("Cannot swap seat {} with itself", args.hero_id_a);
+            return Err(HeroError::InvalidInstruction.into());
+        }
+
+        let nft_account_a = next_account_info(account_info_iter)?;
+        let token_account_a = next_account_info(account_info_iter)?;
+        let nft_account_b = next_account_info(account_info_iter)?;
+        let token_account_b = next_account_info(account_info_iter)?;
+        let history_account = next_account_info(account_info_iter)?;
+        Self::verify_history_account(history_account, repository_account.key, program_id)?;
+
+        // 1. verify ownership of both nfts
+        for (owner_account, nft_account, token_account) in [
+            (owner_account_a, nft_account_a, token_account_a),
+            (owner_account_b, nft_account_b, token_account_b),
+        ].iter() {
+            Self::verify_nft_owner(token_account, nft_account.key, owner_account.key)?;
+        }
+
+        let mut nft_record_a = Self::get_nft_data_from_repository(
+            args.hero_id_a,
+            nft_account_a.key,
+            repository_account.clone(),
+            nft_account_a.clone()
+        )?;
+        let mut nft_record_b = Self::get_nft_data_from_repository(
+            args.hero_id_b,
+            nft_account_b.key,
+            repository_account.clone(),
+            nft_account_b.clone()
+        )?;
+        // a swap moves the listed price like a sale would
+        let config = Self::read_gallery_config(repository_account)?;
+        let now = Clock::get()?.unix_timestamp;
+        Self::verify_cooldown(&nft_record_a, &config, now)?;
+        Self::verify_cooldown(&nft_record_b, &config, now)?;
+
+        // 2. exchange what each owner holds
+        Self::exchange_seats(&mut nft_record_a, &mut nft_record_b);
+        Self::save_nft_data_to_repository(&nft_record_a, repository_account.clone())?;
+        Self::save_nft_data_to_repository(&nft_record_b, repository_account.clone())?;
+
+        // 3. balancing payment
+        if args.lamports_a_to_b > 0 || args.lamports_b_to_a > 0 {
+            let system_program_account = next_account_info(account_info_iter)?;
+            Self::verify_program_account(system_program_account, &system_program::id(), HeroError::InvalidSystemProgram)?;
+            if args.lamports_a_to_b > 0 {
+                Self::sol_transfer(
+                    owner_account_a.clone(),
+                    owner_account_b.clone(),
+                    system_program_account.clone(),
+                    args.lamports_a_to_b
+                )?;
+            }
+            if args.lamports_b_to_a > 0 {
+                Self::sol_transfer(
+                    owner_account_b.clone(),
+                    owner_account_a.clone(),
+                    system_program_account.clone(),
+                    args.lamports_b_to_a
+                )?;
+            }
+        }
+
+        // 4. seat a now belongs to owner b, seat b to owner a
+        Self::record_provenance(
+            history_account,
+            args.hero_id_a,
+            ProvenanceKind::Swap,
+            owner_account_a.key,
+            owner_account_b.key,
+            args.lamports_b_to_a
+        )?;
+        Self::record_provenance(
+            history_account,
+            args.hero_id_b,
+            ProvenanceKind::Swap,
+            owner_account_b.key,
+            owner_account_a.key,
+            args.lamports_a_to_b
+        )?;
+        msg!("Swapped seats {} and {}", args.hero_id_a, args.hero_id_b);
+
+        Ok(())
+    }
+
+    // exchange what the holders of two seats own: the nft, its content and display fields and
+    // the listed price. Seats keep hero_id, last_price, sale_count and last_sold_at.
+    fn exchange_seats(nft_record_a: &mut NFTRecord, nft_record_b: &mut NFTRecord) {
+        std::mem::swap(&mut nft_record_a.key_nft, &mut nft_record_b.key_nft);
+        std::mem::swap(&mut nft_record_a.content_uri, &mut nft_record_b.content_uri);
+        std::mem::swap(&mut nft_record_a.content_hash, &mut nft_record_b.content_hash);
+        std::mem::swap(&mut nft_record_a.caption, &mut nft_record_b.caption);
+        std::mem::swap(&mut nft_record_a.link, &mut nft_record_b.link);
+        std::mem::swap(&mut nft_record_a.accent_color, &mut nft_record_b.accent_color);
+        // hidden content stays hidden wherever it goes
+        std::mem::swap(&mut nft_record_a.moderation, &mut nft_record_b.moderation);
+        std::mem::swap(&mut nft_record_a.placeholder_uri, &mut nft_record_b.placeholder_uri);
+        std::mem::swap(&mut nft_record_a.listed_price, &mut nft_record_b.listed_price);
+    }
+
+    // create a program owned account at a program derived address, paid by payer.
+    // Anyone can send lamports to the address first, which makes `create_account` fail,
+    // so an account already holding lamports is topped up, allocated and assigned instead.
+    fn create_pda_account<'a>(
+        payer: &AccountInfo<'a>,
+        pda_account: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>,
+        space: usize,
+        program_id: &Pubkey,
+        seeds: &[&[u8]]
+    ) -> Result<(), ProgramError> {
+        let rent_exempt_balance = Rent::get()?.minimum_balance(space);
+        if pda_account.lamports() == 0 {
+            let create_ix = system_instruction::create_account(
+                payer.key,
+                pda_account.key,
+                rent_exempt_balance,
+                space as u64,
+                program_id
+            );
+            return invoke_signed(
+                &create_ix,
+                &[payer.clone(), pda_account.clone(), system_program_account.clone()],
+                &[seeds]
+            );
+        }
+
+        let top_up = rent_exempt_balance.saturating_sub(pda_account.lamports());
+        if top_up > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, pda_account.key, top_up),
+                &[payer.clone(), pda_account.clone(), system_program_account.clone()]
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(pda_account.key, space as u64),
+            &[pda_account.clone(), system_program_account.clone()],
+            &[seeds]
+        )?;
+        invoke_signed(
+            &system_instruction::assign(pda_account.key, program_id),
+            &[pda_account.clone(), system_program_account.clone()],
+            &[seeds]
+        )
+    }
+
+    // create associated token account of nft for wallet, paid by funding account
+    #[allow(clippy::too_many_arguments)]
+    fn create_associated_token_account<'a>(
+        funding_account: AccountInfo<'a>,
+        wallet_account: AccountInfo<'a>,
+        associated_token_account: AccountInfo<'a>,
+        nft_mint: AccountInfo<'a>,
+        system_program_account: AccountInfo<'a>,
+        token_program: AccountInfo<'a>,
+        rent_sysvar: AccountInfo<'a>,
+        associated_token_program: AccountInfo<'a>,
+    ) -> Result<(), ProgramError> {
+        let expected_address = get_associated_token_address(wallet_account.key, nft_mint.key);
+        if expected_address != *associated_token_account.key {
+            msg!("Token account to receive is not wallet's associated token account.");
+            return Err(HeroError::InvalidTokenAccount.into());
+        }
+        let ix = create_associated_token_account(funding_account.key, wallet_account.key, nft_mint.key);
+        invoke(
+            &ix,
+            &[
+                funding_account,
+                associated_token_account,
+                wallet_account,
+                nft_mint,
+                system_program_account,
+                token_program,
+                rent_sysvar,
+                associated_token_program,
+            ]
+        )
+    }
+
+    // verify history account belongs to repository. It may not be created yet.
+    fn verify_history_account(
+        history_account: &AccountInfo,
+        repository_account_pk: &Pubkey,
+        program_id: &Pubkey
+    ) -> Result<(), ProgramError> {
+        let (history_address, _) = find_history_address(repository_account_pk, program_id);
+        if history_address != *history_account.key {
+            msg!("History account is not valid for repository");
+            return Err(HeroError::InvalidHistoryAccount.into());
+        }
+        if history_account.data_is_empty() {
+            return Ok(());
+        }
+        if history_account.owner != program_id || history_account.data_len() < HISTORY_SIZE {
+            msg!("History account is not valid for repository");
+            return Err(HeroError::InvalidHistoryAccount.into());
+        }
+        Ok(())
+    }
+
+    // append an entry to the history of a seat, unless history account isn't created yet
+    fn record_provenance(
+        history_account: &AccountInfo,
+        hero_id: u8,
+        kind: ProvenanceKind,
+        seller: &Pubkey,
+        buyer: &Pubkey,
+        price: u64
+    ) -> Result<(), ProgramError> {
+        if history_account.data_is_empty() {
+            msg!("History account is not created, seat {} provenance is not recorded", hero_id);
+            return Ok(());
+        }
+        let clock = Clock::get()?;
+        let start = hero_id as usize * SEAT_HISTORY_SIZE;
+        let end = start + SEAT_HISTORY_SIZE;
+        let mut data = history_account.data.borrow_mut();
+        let mut seat_history = SeatHistory::unpack(&data[start..end])?;
+        seat_history.push(ProvenanceEntry {
+            kind,
+            seller: *seller,
+            buyer: *buyer,
+            price,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp
+        });
+        seat_history.pack(&mut data[start..end])
+    }
+
+    // verify graveyard account belongs to repository and was created by `CreateGraveyard`
+    fn verify_graveyard_account(
+        graveyard_account: &AccountInfo,
+        repository_account_pk: &Pubkey,
+        program_id: &Pubkey
+    ) -> Result<(), ProgramError> {
+        let (graveyard_address, _) = find_graveyard_address(repository_account_pk, program_id);
+        if graveyard_address != *graveyard_account.key
+            || graveyard_account.owner != program_id
+            || graveyard_account.data_len() != GRAVEYARD_SIZE
+        {
+            msg!("Graveyard account is not valid for gallery");
+            return Err(HeroError::InvalidGraveyardAccount.into());
+        }
+        Ok(())
+    }
+
+    // append a dead nft to the graveyard page following the last burial,
+    // creating the page paid by payer when the previous one is full
+    fn bury_nft<'a>(
+        graveyard_account: &AccountInfo<'a>,
+        graveyard_page: &AccountInfo<'a>,
+        payer: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>,
+        repository_account_pk: &Pubkey,
+        dead_nft: DeadNFT,
+        program_id: &Pubkey
+    ) -> Result<(), ProgramError> {
+        let mut graveyard = GraveyardIndex::try_from_slice(&graveyard_account.data.borrow())?;
+        let (page, slot) = graveyard.locate(graveyard.buried);
+        let (page_address, bump_seed) = find_graveyard_page_address(repository_account_pk, page, program_id);
+        if page_address != *graveyard_page.key {
+            msg!("Graveyard page should be {}", page_address);
+            return Err(HeroError::InvalidGraveyardAccount.into());
+        }
+        if graveyard_page.data_is_empty() {
+            Self::create_pda_account(
+                payer,
+                graveyard_page,
+                system_program_account,
+                graveyard.page_size(),
+                program_id,
+                &[GRAVEYARD_SEED, repository_account_pk.as_ref(), &page.to_le_bytes(), &[bump_seed]]
+            )?;
+        } else if graveyard_page.owner != program_id || graveyard_page.data_len() != graveyard.page_size() {
+            msg!("Graveyard page is not valid for gallery");
+            return Err(HeroError::InvalidGraveyardAccount.into());
+        }
+
+        let start = slot * DEAD_NFT_SIZE;
+        dead_nft.serialize(&mut &mut graveyard_page.data.borrow_mut()[start..start + DEAD_NFT_SIZE])?;
+        graveyard.buried = graveyard.buried.checked_add(1).ok_or(ProgramError::InvalidAccountData)?;
+        graveyard.serialize(&mut &mut graveyard_account.data.borrow_mut()[..])?;
+        Ok(())
+    }
+
+    // write the seats of a legacy repository in the current layout
+    fn migrate_seats(legacy_data: &[u8], data: &mut [u8]) -> Result<(), ProgramError> {
+        for (hero_id, mut legacy_seat) in legacy_data.chunks_exact(LEGACY_NFT_RECORD_SIZE).take(NFT_COUNT).enumerate() {
+            // trailing bytes of a slot may be left over from a longer record
+            let legacy_record = LegacyNFTRecord::deserialize(&mut legacy_seat)?;
+            if legacy_record.key_nft == Pubkey::default() {
+                continue;
+            }
+            let start = hero_id * NFT_RECORD_SIZE;
+            NFTRecord::from(legacy_record).serialize(&mut &mut data[start..start + NFT_RECORD_SIZE])?;
+        }
+        Ok(())
+    }
+
+    // move all lamports of a program owned account to destination and clear its data,
+    // the runtime deletes it after the transaction
+    fn close_account(account: &AccountInfo, destination: &AccountInfo) -> Result<(), ProgramError> {
+        let lamports = destination.lamports()
+            .checked_add(account.lamports())
+            .ok_or(ProgramError::InvalidArgument)?;
+        **destination.try_borrow_mut_lamports()? = lamports;
+        **account.try_borrow_mut_lamports()? = 0;
+        for byte in account.data.borrow_mut().iter_mut() {
+            *byte = 0;
+        }
+        Ok(())
+    }
+
+    // transfer sol
+    fn sol_transfer<'a>(
+        source: AccountInfo<'a>,
+        destination: AccountInfo<'a>,
+        system_program: AccountInfo<'a>,
+        amount: u64,
+    ) -> Result<(), ProgramError> {
+        let ix = solana_program::system_instruction::transfer(source.key, destination.key, amount);
+        invoke(&ix, &[source, destination, system_program])
+    }
+
+    // verify program account passed by caller is the expected program
+    fn verify_program_account(
+        program_account: &AccountInfo,
+        expected_program_id: &Pubkey,
+        error: HeroError
+    ) -> Result<(), ProgramError> {
+        if program_account.key != expected_program_id || !program_account.executable {
+            msg!("Program account {} should be {}", program_account.key, expected_program_id);
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    // verify mint or token account is owned by token program
+    fn verify_token_program_owned(account: &AccountInfo) -> Result<(), ProgramError> {
+        if *account.owner != spl_token::id() {
+            msg!("Account {} is not owned by token program", account.key);
+            return Err(HeroError::InvalidTokenAccountOwner.into());
+        }
+        Ok(())
+    }
+
+    // byte range of a seat inside repository account
+    fn record_range(hero_id: u8) -> Result<(usize, usize), ProgramError> {
+        if hero_id as usize >= NFT_COUNT {
+            msg!("hero_id {} is out of range", hero_id);
+            return Err(HeroError::HeroOverflow.into());
+        }
+        let start: usize = hero_id as usize * NFT_RECORD_SIZE;
+        Ok((start, start + NFT_RECORD_SIZE))
+    }
+
+    // repository account should be allocated for all seats
+    fn verify_repository_size(end: usize, repository_account: &AccountInfo) -> Result<(), ProgramError> {
+        // seats of a legacy repository are at other offsets and it has no room for the config
+        if repository_account.data_len() > 0 && repository_account.data_len() < REPOSITORY_SIZE {
+            msg!("Repository account has the legacy layout, run MigrateRepository");
+            return Err(HeroError::RepositoryNotMigrated.into());
+        }
+        if repository_account.data_len() < end {
+            msg!("Repository account is not allocated for all seats");
+            return Err(HeroError::UninitializedRepository.into());
+        }
+        Ok(())
+    }
+
+    // read gallery config from repository account
+    fn read_gallery_config(repository_account: &AccountInfo) -> Result<GalleryConfig, ProgramError> {
+        let end = CONFIG_OFFSET + GALLERY_CONFIG_SIZE;
+        Self::verify_repository_size(end, repository_account)?;
+        let config = GalleryConfig::deserialize(&mut &repository_account.data.borrow()[CONFIG_OFFSET..end])?;
+        Ok(config)
+    }
+
+    // save gallery config to repository account, clearing what was there
+    fn save_gallery_config(config: &GalleryConfig, repository_account: &AccountInfo) -> Result<(), ProgramError> {
+        let end = CONFIG_OFFSET + GALLERY_CONFIG_SIZE;
+        Self::verify_repository_size(end, repository_account)?;
+        let mut data = repository_account.data.borrow_mut();
+        for byte in data[CONFIG_OFFSET..end].iter_mut() {
+            *byte = 0;
+        }
+        config.serialize(&mut &mut data[CONFIG_OFFSET..end]).map_err(|_| HeroError::InvalidConfig)?;
+        Ok(())
+    }
+
+    // verify content uri against the allowed prefixes of gallery config
+    fn verify_content_uri(content_uri: &str, repository_account: &AccountInfo) -> Result<(), ProgramError> {
+        let config = Self::read_gallery_config(repository_account)?;
+        if content_uri.len() > MAX_CONTENT_URI_LENGTH || !config.is_allowed_uri(content_uri) {
+            msg!("content_uri is not allowed by gallery config");
+            return Err(HeroError::InvalidUri.into());
+        }
+        Ok(())
+    }
+
+    // verify seat isn't sold or repriced before the cooldown after its last sale ends
+    fn verify_cooldown(nft_record: &NFTRecord, config: &GalleryConfig, now: i64) -> Result<(), ProgramError> {
+        let cooldown_ends_at = config.cooldown_ends_at(nft_record.last_sold_at);
+        if now < cooldown_ends_at {
+            msg!("Seat {} is cooling down until {}", nft_record.hero_id, cooldown_ends_at);
+            return Err(HeroError::SeatCoolingDown.into());
+        }
+        Ok(())
+    }
+
+    // verify caption and link of a seat are bounded and printable, link must be https
+    fn verify_display_fields(caption: &str, link: &str) -> Result<(), ProgramError> {
+        if caption.len() > MAX_CAPTION_LENGTH || caption.chars().any(char::is_control) {
+            msg!("caption must be at most {} printable bytes", MAX_CAPTION_LENGTH);
+            return Err(HeroError::InvalidContent.into());
+        }
+        if link.len() > MAX_LINK_LENGTH || link.chars().any(char::is_control) {
+            msg!("link must be at most {} printable bytes", MAX_LINK_LENGTH);
+            return Err(HeroError::InvalidContent.into());
+        }
+        if !link.is_empty() && !link.starts_with("https://") {
+            msg!("link must be an https url");
+            return Err(HeroError::InvalidUri.into());
+        }
+        Ok(())
+    }
+
+    // unpack token account holding exactly one token of the nft
+    fn unpack_nft_holding(token_account: &AccountInfo, nft_mint_pk: &Pubkey) -> Result<TokenAccount, ProgramError> {
+        Self::verify_token_program_owned(token_account)?;
+        let token_account_info = TokenAccount::unpack(&token_account.data.borrow())?;
+        if token_account_info.mint != *nft_mint_pk || token_account_info.amount != 1 {
+            msg!("Token account doesn't hold the NFT.");
+            return Err(HeroError::NotNFTOwner.into());
+        }
+        Ok(token_account_info)
+    }
+
+    // verify token account holds the nft: exactly one token, not frozen and not delegated
+    // to anyone who could move it. Returns the owner of the token account.
+    fn verify_nft_holding(token_account: &AccountInfo, nft_mint_pk: &Pubkey) -> Result<Pubkey, ProgramError> {
+        let token_account_info = Self::unpack_nft_holding(token_account, nft_mint_pk)?;
+        if token_account_info.is_frozen() {
+            msg!("Token account of the NFT is frozen.");
+            return Err(HeroError::NotNFTOwner.into());
+        }
+        if token_account_info.delegate.is_some() {
+            msg!("NFT is delegated to another account.");
+            return Err(HeroError::NotNFTOwner.into());
+        }
+        Ok(token_account_info.owner)
+    }
+
+    // verify owner holds the nft in token account, see `verify_nft_holding`
+    fn verify_nft_owner(
+        token_account: &AccountInfo,
+        nft_mint_pk: &Pubkey,
+        owner_pk: &Pubkey
+    ) -> Result<(), ProgramError> {
+        if Self::verify_nft_holding(token_account, nft_mint_pk)? != *owner_pk {
+            msg!("NFT is not owned by {}.", owner_pk);
+            return Err(HeroError::NotNFTOwner.into());
+        }
+        Ok(())
+    }
+
+    // verify setter may update the seat: the holder of the seat nft, or admin overriding it.
+    // Returns the seat.
+    fn verify_seat_setter<'a>(
+        setter_account: &AccountInfo<'a>,
+        repository_account: &AccountInfo<'a>,
+        nft_account: &AccountInfo<'a>,
+        token_account: &AccountInfo<'a>,
+        hero_id: u8,
+        key_nft: &Pubkey,
+        program_id: &Pubkey
+    ) -> Result<NFTRecord, ProgramError> {
+        // token account must hold the seat nft
+        let owner = Self::verify_nft_holding(token_account, nft_account.key)?;
+
+        // owner updates its own seat, admin may override any seat
+        if owner != *setter_account.key {
+            if !Self::is_admin(setter_account.key, repository_account.key, program_id)? {
+                msg!("NFT is not owned by signer.");
+                return Err(HeroError::NotNFTOwner.into());
+            }
+            msg!("Admin override of seat {}", hero_id);
+        }
+
+        Self::get_nft_data_from_repository(
+            hero_id,
+            key_nft,
+            repository_account.clone(),
+            nft_account.clone()
+        )
+    }
+
+    // read a seat from repository account, empty or not
+    fn read_nft_record<'a>(
+        hero_id: u8,
+        repository_account: AccountInfo<'a>,
+    ) -> Result<NFTRecord, ProgramError> {
+        let (start, end) = Self::record_range(hero_id)?;
+        Self::verify_repository_size(end, &repository_account)?;
+        let nft_record: NFTRecord = NFTRecord::deserialize(&mut &repository_account.data.borrow()[start..end])?;
+        Ok(nft_record)
+    }
+
+    // fetch nft data from repository account with hero_id
+    fn get_nft_data_from_repository<'a>(
+        hero_id: u8,
+        key_nft: &Pubkey,
+        repository_account: AccountInfo<'a>,
+        nft_account: AccountInfo<'a>,
+    ) -> Result<NFTRecord, ProgramError> {
+        let nft_record = Self::read_nft_record(hero_id, repository_account)?;
+
+        if nft_record.is_empty() {
+            msg!("Seat {} is empty", hero_id);
+            return Err(HeroError::SeatEmpty.into());
+        }
+
+        if nft_record.hero_id != hero_id {
+            msg!("Seat {} holds record of hero {}", hero_id, nft_record.hero_id);
+            return Err(HeroError::HeroIdMismatch.into());
+        }
+        
+        if nft_record.key_nft != *key_nft || nft_record.key_nft != *nft_account.key {
+            msg!("NFT Key dismatch.");
             return Err(HeroError::InvalidNFTKey.into());
         }
         Ok(nft_record)
     }
 
+    // verify mint is an initialized 0-decimal, supply-1 token
+    fn verify_nft_mint(nft_mint_account: &AccountInfo) -> Result<(), ProgramError> {
+        if *nft_mint_account.owner != spl_token::id() {
+            msg!("NFT mint is not owned by token program");
+            return Err(HeroError::InvalidMint.into());
+        }
+        let mint = Mint::unpack(&nft_mint_account.data.borrow())
+            .map_err(|_| HeroError::InvalidMint)?;
+        if mint.decimals != 0 || mint.supply != 1 {
+            msg!("NFT mint should have 0 decimals and supply 1");
+            return Err(HeroError::InvalidMint.into());
+        }
+        Ok(())
+    }
+
+    // verify the nft replacing a sold one is a real nft, not held by another seat,
+    // and sent from a token account of its mint
+    fn verify_new_seat_nft(
+        hero_id: u8,
+        new_nft_mint: &AccountInfo,
+        nft_token_account_to_send: &AccountInfo,
+        repository_account: &AccountInfo
+    ) -> Result<(), ProgramError> {
+        Self::verify_nft_mint(new_nft_mint)?;
+        Self::verify_nft_key_unique(hero_id, new_nft_mint.key, repository_account.clone())?;
+        Self::verify_token_program_owned(nft_token_account_to_send)?;
+        let send_account_info = TokenAccount::unpack(&nft_token_account_to_send.data.borrow())?;
+        if send_account_info.mint != *new_nft_mint.key {
+            msg!("Token account to send does not hold new NFT.");
+            return Err(HeroError::InvalidTokenAccount.into());
+        }
+        Ok(())
+    }
+
+    // make sure no other seat holds this nft
+    fn verify_nft_key_unique<'a>(
+        hero_id: u8,
+        key_nft: &Pubkey,
+        repository_account: AccountInfo<'a>,
+    ) -> Result<(), ProgramError> {
+        for seat_id in 0..NFT_COUNT as u8 {
+            if seat_id == hero_id {
+                continue;
+            }
+            let nft_record = Self::read_nft_record(seat_id, repository_account.clone())?;
+            if nft_record.key_nft == *key_nft {
+                msg!("NFT is already assigned to seat {}", seat_id);
+                return Err(HeroError::DuplicateNFTKey.into());
+            }
+        }
+        Ok(())
+    }
+
     // modify nft data to repository
     fn save_nft_data_to_repository<'a>(
         nft_record: &NFTRecord,
         repository_account: AccountInfo<'a>,
     ) -> Result<(), ProgramError> {
-        let start: usize = nft_record.hero_id as usize * NFT_RECORD_SIZE;
-        let end: usize = start + NFT_RECORD_SIZE;
+        let (start, end) = Self::record_range(nft_record.hero_id)?;
+        Self::verify_repository_size(end, &repository_account)?;
         nft_record.serialize(&mut &mut repository_account.data.borrow_mut()[start..end])?;
         Ok(())
     }
@@ -354,41 +2141,136 @@ impl Processor {
     // update metadata account
     fn update_metadata_old_nft<'a>(
         admin_account: AccountInfo<'a>,
+        gallery_authority: AccountInfo<'a>,
+        repository_account_pk: &Pubkey,
         old_nft_mint: AccountInfo<'a>,
         old_nft_metadata_account: AccountInfo<'a>,
         token_metadata_program: AccountInfo<'a>,
-        args: &BuyRecordArgs,
+        dead_uri: &str,
+        dead_name: &str,
+        program_id: &Pubkey
     ) -> Result<(), ProgramError> {
         
-        let mut old_metadata = Metadata::from_account_info(&old_nft_metadata_account).unwrap();
         // verify validation of metadata account
-        if old_nft_metadata_account.owner != token_metadata_program.key 
-            || old_metadata.mint != *old_nft_mint.key
-        {
+        if old_nft_metadata_account.owner != token_metadata_program.key {
+            msg!("nft_metadata_account is not owned by token metadata program");
+            return Err(HeroError::InvalidMetadata.into());
+        }
+        let mut old_metadata = Metadata::from_account_info(&old_nft_metadata_account)
+            .map_err(|_| HeroError::InvalidMetadata)?;
+        if old_metadata.mint != *old_nft_mint.key {
             msg!("nft_metadata_account is not valid account");
-            return Err(ProgramError::InvalidAccountData);
+            return Err(HeroError::InvalidMetadata.into());
         }
-        old_metadata.data.uri = args.dead_uri.to_string();
-        old_metadata.data.name = args.dead_name.to_string();
+        old_metadata.data.uri = dead_uri.to_string();
+        old_metadata.data.name = dead_name.to_string();
+
+        // seats synced by UpdateRecord hand update authority to the gallery, give it back to admin
+        let update_authority = if old_metadata.update_authority == *gallery_authority.key {
+            gallery_authority.clone()
+        } else {
+            admin_account.clone()
+        };
         let update_metadata_instruction = update_metadata_accounts(
             spl_token_metadata::id(),               // program_id
             *old_nft_metadata_account.key,          // metadata_account
-            *admin_account.key,                     // update_authority
+            *update_authority.key,                  // update_authority
             Some(*admin_account.key),               // new_update_authority
             Some(old_metadata.data),                // data
             Some(true)                              // primary_sale_happened
         );
-        invoke(
+        let (_, bump_seed) = find_gallery_authority_address(repository_account_pk, program_id);
+        invoke_signed(
             &update_metadata_instruction,
             &[
                 old_nft_metadata_account.clone(),
-                admin_account.clone(),
-                old_nft_metadata_account.clone(),
+                update_authority,
                 token_metadata_program.clone()
-            ]
+            ],
+            &[&[GALLERY_AUTHORITY_SEED, repository_account_pk.as_ref(), &[bump_seed]]]
+        )
+    }
+
+    // verify gallery authority is the program derived address of repository, return its bump seed
+    fn verify_gallery_authority(
+        gallery_authority: &AccountInfo,
+        repository_account_pk: &Pubkey,
+        program_id: &Pubkey
+    ) -> Result<u8, ProgramError> {
+        let (gallery_authority_address, bump_seed) = find_gallery_authority_address(
+            repository_account_pk,
+            program_id
+        );
+        if gallery_authority_address != *gallery_authority.key {
+            msg!("Gallery authority is not valid for repository");
+            return Err(HeroError::InvalidGalleryAuthority.into());
+        }
+        Ok(bump_seed)
+    }
+
+    // point nft metadata uri at content_uri, signed by gallery authority
+    fn sync_metadata_uri<'a>(
+        nft_mint: &AccountInfo<'a>,
+        nft_metadata_account: &AccountInfo<'a>,
+        gallery_authority: &AccountInfo<'a>,
+        token_metadata_program: &AccountInfo<'a>,
+        repository_account_pk: &Pubkey,
+        content_uri: &str,
+        program_id: &Pubkey
+    ) -> Result<(), ProgramError> {
+        let bump_seed = Self::verify_gallery_authority(gallery_authority, repository_account_pk, program_id)?;
+
+        if nft_metadata_account.owner != token_metadata_program.key {
+            msg!("nft_metadata_account is not owned by token metadata program");
+            return Err(HeroError::InvalidMetadata.into());
+        }
+        let mut metadata = Metadata::from_account_info(nft_metadata_account)
+            .map_err(|_| HeroError::InvalidMetadata)?;
+        if metadata.mint != *nft_mint.key {
+            msg!("nft_metadata_account is not valid account");
+            return Err(HeroError::InvalidMetadata.into());
+        }
+        if metadata.update_authority != *gallery_authority.key {
+            msg!("Update authority of nft metadata is not the gallery");
+            return Err(HeroError::InvalidGalleryAuthority.into());
+        }
+        if content_uri.len() > MAX_URI_LENGTH {
+            msg!("content_uri is too long for nft metadata");
+            return Err(HeroError::InvalidMetadata.into());
+        }
+
+        metadata.data.uri = content_uri.to_string();
+        let update_metadata_instruction = update_metadata_accounts(
+            spl_token_metadata::id(),               // program_id
+            *nft_metadata_account.key,              // metadata_account
+            *gallery_authority.key,                 // update_authority
+            None,                                   // new_update_authority
+            Some(metadata.data),                    // data
+            None                                    // primary_sale_happened
+        );
+        invoke_signed(
+            &update_metadata_instruction,
+            &[
+                nft_metadata_account.clone(),
+                gallery_authority.clone(),
+                token_metadata_program.clone()
+            ],
+            &[&[GALLERY_AUTHORITY_SEED, repository_account_pk.as_ref(), &[bump_seed]]]
         )
     }
 
+    // whether account is admin of repository
+    fn is_admin(
+        account_pk: &Pubkey,
+        repository_account_pk: &Pubkey,
+        program_id: &Pubkey
+    ) -> Result<bool, ProgramError> {
+        let admin_repo_account_pubkey = Pubkey::create_with_seed(
+            account_pk, REPO_ACCOUNT_SEED, program_id
+        )?;
+        Ok(admin_repo_account_pubkey == *repository_account_pk)
+    }
+
     // verify repository editable authority
     fn verify_admin_authority<'a>(
         admin_account_pk: &Pubkey,
@@ -409,3 +2291,911 @@ impl Processor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_program::{clock::Epoch, program_option::COption},
+        spl_token::state::AccountState,
+    };
+
+    // storage behind an AccountInfo
+    struct TestAccount {
+        key: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        owner: Pubkey,
+        executable: bool,
+    }
+
+    impl TestAccount {
+        fn new(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> Self {
+            Self { key, lamports: 0, data, owner, executable: false }
+        }
+
+        fn program(program_id: Pubkey) -> Self {
+            Self { executable: true, ..Self::new(program_id, Pubkey::new_unique(), vec![]) }
+        }
+
+        fn info(&mut self, is_signer: bool) -> AccountInfo {
+            AccountInfo::new(
+                &self.key,
+                is_signer,
+                true,
+                &mut self.lamports,
+                &mut self.data,
+                &self.owner,
+                self.executable,
+                Epoch::default()
+            )
+        }
+    }
+
+    fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> TokenAccount {
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None
+        }
+    }
+
+    fn token_test_account(token_account: TokenAccount) -> TestAccount {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(token_account, &mut data).unwrap();
+        TestAccount::new(Pubkey::new_unique(), spl_token::id(), data)
+    }
+
+    fn mint_test_account(decimals: u8, supply: u64) -> TestAccount {
+        let mint = Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None
+        };
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(mint, &mut data).unwrap();
+        TestAccount::new(Pubkey::new_unique(), spl_token::id(), data)
+    }
+
+    fn dead_nft(hero_id: u8) -> DeadNFT {
+        DeadNFT {
+            mint: Pubkey::new_unique(),
+            hero_id,
+            former_owner: Pubkey::new_unique(),
+            sale_price: hero_id as u64,
+            died_at: 0
+        }
+    }
+
+    fn graveyard_account(program_id: &Pubkey, repository: &Pubkey, page_capacity: u16) -> TestAccount {
+        let (graveyard_address, _) = find_graveyard_address(repository, program_id);
+        let graveyard = GraveyardIndex { buried: 0, page_capacity };
+        TestAccount::new(graveyard_address, *program_id, graveyard.try_to_vec().unwrap())
+    }
+
+    fn graveyard_page(program_id: &Pubkey, repository: &Pubkey, page: u32, page_capacity: u16) -> TestAccount {
+        let (page_address, _) = find_graveyard_page_address(repository, page, program_id);
+        TestAccount::new(page_address, *program_id, vec![0; page_capacity as usize * DEAD_NFT_SIZE])
+    }
+
+    #[test]
+    fn test_bury_nft_fills_pages() {
+        let program_id = Pubkey::new_unique();
+        let repository = Pubkey::new_unique();
+        let mut graveyard = graveyard_account(&program_id, &repository, 2);
+        let mut pages: Vec<TestAccount> = (0..3)
+            .map(|page| graveyard_page(&program_id, &repository, page, 2))
+            .collect();
+        let mut payer = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        let mut system_program_account = TestAccount::program(system_program::id());
+        let graveyard_info = graveyard.info(false);
+        let payer = payer.info(true);
+        let system_program_account = system_program_account.info(false);
+        let page_infos: Vec<AccountInfo> = pages.iter_mut().map(|page| page.info(false)).collect();
+        Processor::verify_graveyard_account(&graveyard_info, &repository, &program_id).unwrap();
+
+        for hero_id in 0..5 {
+            let page = &page_infos[hero_id as usize / 2];
+            // the page of the next burial only
+            let other_page = &page_infos[(hero_id as usize / 2 + 1) % 3];
+            let bury = |page| Processor::bury_nft(
+                &graveyard_info,
+                page,
+                &payer,
+                &system_program_account,
+                &repository,
+                dead_nft(hero_id),
+                &program_id
+            );
+            assert_eq!(bury(other_page), Err(HeroError::InvalidGraveyardAccount.into()));
+            bury(page).unwrap();
+        }
+        let index = GraveyardIndex::try_from_slice(&graveyard_info.data.borrow()).unwrap();
+        assert_eq!(index, GraveyardIndex { buried: 5, page_capacity: 2 });
+        assert_eq!(index.page_count(), 3);
+        // every dead nft is kept, the last page has room for one more
+        let hero_ids: Vec<Vec<u8>> = page_infos
+            .iter()
+            .map(|page| {
+                page.data
+                    .borrow()
+                    .chunks_exact(DEAD_NFT_SIZE)
+                    .map(|mut chunk| DeadNFT::deserialize(&mut chunk).unwrap().hero_id)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(hero_ids, vec![vec![0, 1], vec![2, 3], vec![4, 0]]);
+    }
+
+    #[test]
+    fn test_bury_nft_foreign_page() {
+        let program_id = Pubkey::new_unique();
+        let repository = Pubkey::new_unique();
+        let mut graveyard = graveyard_account(&program_id, &repository, 2);
+        let mut payer = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        let mut system_program_account = TestAccount::program(system_program::id());
+        let graveyard_info = graveyard.info(false);
+        let payer = payer.info(true);
+        let system_program_account = system_program_account.info(false);
+
+        let mut foreign = graveyard_page(&program_id, &repository, 0, 2);
+        foreign.owner = Pubkey::new_unique();
+        let mut resized = graveyard_page(&program_id, &repository, 0, 3);
+        let foreign = foreign.info(false);
+        let resized = resized.info(false);
+        for page in [&foreign, &resized].iter() {
+            assert_eq!(
+                Processor::bury_nft(
+                    &graveyard_info,
+                    page,
+                    &payer,
+                    &system_program_account,
+                    &repository,
+                    dead_nft(0),
+                    &program_id
+                ),
+                Err(HeroError::InvalidGraveyardAccount.into())
+            );
+        }
+        assert_eq!(GraveyardIndex::try_from_slice(&graveyard_info.data.borrow()).unwrap().buried, 0);
+    }
+
+    #[test]
+    fn test_verify_graveyard_account() {
+        let program_id = Pubkey::new_unique();
+        let repository = Pubkey::new_unique();
+        let (graveyard_address, _) = find_graveyard_address(&repository, &program_id);
+        let invalid: Result<(), ProgramError> = Err(HeroError::InvalidGraveyardAccount.into());
+
+        let mut short = TestAccount::new(graveyard_address, program_id, vec![0; GRAVEYARD_SIZE - 1]);
+        assert_eq!(Processor::verify_graveyard_account(&short.info(false), &repository, &program_id), invalid);
+
+        let mut other = TestAccount::new(Pubkey::new_unique(), program_id, vec![0; GRAVEYARD_SIZE]);
+        assert_eq!(Processor::verify_graveyard_account(&other.info(false), &repository, &program_id), invalid);
+
+        let mut foreign = TestAccount::new(graveyard_address, Pubkey::new_unique(), vec![0; GRAVEYARD_SIZE]);
+        assert_eq!(Processor::verify_graveyard_account(&foreign.info(false), &repository, &program_id), invalid);
+    }
+
+    #[test]
+    fn test_history_account_not_created() {
+        let program_id = Pubkey::new_unique();
+        let repository = Pubkey::new_unique();
+        let (history_address, _) = find_history_address(&repository, &program_id);
+
+        let mut history = TestAccount::new(history_address, system_program::id(), vec![]);
+        let history_info = history.info(false);
+        Processor::verify_history_account(&history_info, &repository, &program_id).unwrap();
+        // provenance is skipped rather than failing the sale
+        Processor::record_provenance(
+            &history_info,
+            0,
+            ProvenanceKind::Sale,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            1
+        ).unwrap();
+
+        let mut other = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        assert_eq!(
+            Processor::verify_history_account(&other.info(false), &repository, &program_id),
+            Err(HeroError::InvalidHistoryAccount.into())
+        );
+    }
+
+    fn repository_account(program_id: &Pubkey, admin: &Pubkey) -> TestAccount {
+        let key = Pubkey::create_with_seed(admin, REPO_ACCOUNT_SEED, program_id).unwrap();
+        TestAccount::new(key, *program_id, vec![0; REPOSITORY_SIZE])
+    }
+
+    fn legacy_record(hero_id: u8) -> LegacyNFTRecord {
+        LegacyNFTRecord {
+            hero_id,
+            content_uri: "https://arweave.net/hero".to_string(),
+            key_nft: Pubkey::new_unique(),
+            last_price: 1,
+            listed_price: 2
+        }
+    }
+
+    struct Seat {
+        repository: TestAccount,
+        nft: TestAccount,
+        token: TestAccount,
+        nft_record: NFTRecord
+    }
+
+    // seat 0 in the repository of admin, its nft held by owner
+    fn occupied_seat(program_id: &Pubkey, admin: &Pubkey, owner: &Pubkey) -> Seat {
+        let nft_record = NFTRecord::from(legacy_record(0));
+        let mut repository = repository_account(program_id, admin);
+        nft_record.serialize(&mut &mut repository.data[..NFT_RECORD_SIZE]).unwrap();
+        Seat {
+            repository,
+            nft: TestAccount::new(nft_record.key_nft, spl_token::id(), vec![]),
+            token: token_test_account(token_account(&nft_record.key_nft, owner, 1)),
+            nft_record
+        }
+    }
+
+    #[test]
+    fn test_update_config_round_trip() {
+        let program_id = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let mut admin = TestAccount::new(admin_key, system_program::id(), vec![]);
+        let mut repository = repository_account(&program_id, &admin_key);
+        let args = UpdateConfigArgs {
+            dead_name_template: "Fallen Hero #{hero_id} ({sale_count})".to_string(),
+            dead_uri_base: "https://example.com/dead/".to_string(),
+            allowed_uri_prefixes: vec!["ar://".to_string()],
+            cooldown_seconds: 60
+        };
+        let accounts = [admin.info(true), repository.info(false)];
+        Processor::process(&program_id, &accounts, &HeroInstruction::UpdateConfig(args.clone()).pack()).unwrap();
+
+        let config = Processor::read_gallery_config(&accounts[1]).unwrap();
+        assert!(config.is_initialized);
+        assert_eq!(config.dead_name(3, 2), "Fallen Hero #3 (2)");
+        assert_eq!(config.dead_uri(3, 2), "https://example.com/dead/3-2.json");
+        assert_eq!(config.allowed_uri_prefixes, args.allowed_uri_prefixes);
+        assert_eq!(config.cooldown_seconds, 60);
+
+        // generated names must fit in token metadata
+        let too_long = UpdateConfigArgs {
+            dead_name_template: "{hero_id}".repeat(MAX_NAME_LENGTH),
+            ..args
+        };
+        assert_eq!(
+            Processor::process(&program_id, &accounts, &HeroInstruction::UpdateConfig(too_long).pack()),
+            Err(HeroError::InvalidConfig.into())
+        );
+        assert_eq!(Processor::read_gallery_config(&accounts[1]).unwrap(), config);
+    }
+
+    #[test]
+    fn test_verify_repository_size_legacy_layout() {
+        let mut repository = TestAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), vec![0; NFT_COUNT * LEGACY_NFT_RECORD_SIZE]);
+        assert_eq!(
+            Processor::read_gallery_config(&repository.info(false)),
+            Err(HeroError::RepositoryNotMigrated.into())
+        );
+        let mut repository = TestAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), vec![0; REPOSITORY_SIZE]);
+        assert_eq!(Processor::read_gallery_config(&repository.info(false)), Ok(GalleryConfig::default()));
+    }
+
+    #[test]
+    fn test_migrate_seats() {
+        let record = legacy_record(1);
+        let mut legacy_data = vec![0; NFT_COUNT * LEGACY_NFT_RECORD_SIZE];
+        let legacy_seat = &mut legacy_data[LEGACY_NFT_RECORD_SIZE..2 * LEGACY_NFT_RECORD_SIZE];
+        record.serialize(&mut &mut legacy_seat[..]).unwrap();
+        // left over from a longer record
+        legacy_seat[LEGACY_NFT_RECORD_SIZE - 1] = 0xff;
+
+        let mut data = vec![0; REPOSITORY_SIZE];
+        Processor::migrate_seats(&legacy_data, &mut data).unwrap();
+        let nft_record = NFTRecord::deserialize(&mut &data[NFT_RECORD_SIZE..2 * NFT_RECORD_SIZE]).unwrap();
+        assert_eq!(nft_record, NFTRecord::from(record));
+        assert_eq!(nft_record.sale_count, 0);
+        assert!(data[..NFT_RECORD_SIZE].iter().all(|byte| *byte == 0));
+        assert!(data[2 * NFT_RECORD_SIZE..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_migrate_repository_restores_seats() {
+        let program_id = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let mut admin = TestAccount::new(admin_key, system_program::id(), vec![]);
+        let mut repository = repository_account(&program_id, &admin_key);
+        let (migration_address, _) = find_migration_address(&repository.key, &program_id);
+        let mut stash = vec![0; REPOSITORY_SIZE];
+        NFTRecord::from(legacy_record(0)).serialize(&mut &mut stash[..NFT_RECORD_SIZE]).unwrap();
+        let mut migration = TestAccount::new(migration_address, program_id, stash.clone());
+        migration.lamports = 10;
+
+        let accounts = [admin.info(true), repository.info(false), migration.info(false)];
+        Processor::process(&program_id, &accounts, &HeroInstruction::MigrateRepository.pack()).unwrap();
+        assert_eq!(&accounts[1].data.borrow()[..], &stash[..]);
+        assert!(accounts[2].data.borrow().iter().all(|byte| *byte == 0));
+        assert_eq!(accounts[2].lamports(), 0);
+        assert_eq!(accounts[0].lamports(), 10);
+
+        // the restored repository isn't overwritten
+        assert_eq!(
+            Processor::process(&program_id, &accounts, &HeroInstruction::MigrateRepository.pack()),
+            Err(ProgramError::AccountAlreadyInitialized)
+        );
+    }
+
+    #[test]
+    fn test_nft_holding_frozen_or_delegated() {
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut frozen = token_account(&mint, &owner, 1);
+        frozen.state = AccountState::Frozen;
+        let mut delegated = token_account(&mint, &owner, 1);
+        delegated.delegate = COption::Some(Pubkey::new_unique());
+        delegated.delegated_amount = 1;
+
+        // a seller's nft is only retired by a sale
+        for token in [frozen, delegated].iter() {
+            let mut account = token_test_account(*token);
+            let info = account.info(false);
+            assert_eq!(Processor::unpack_nft_holding(&info, &mint).unwrap().owner, owner);
+            assert_eq!(Processor::verify_nft_holding(&info, &mint), Err(HeroError::NotNFTOwner.into()));
+        }
+
+        for token in [token_account(&mint, &owner, 0), token_account(&Pubkey::new_unique(), &owner, 1)].iter() {
+            let mut account = token_test_account(*token);
+            assert_eq!(
+                Processor::unpack_nft_holding(&account.info(false), &mint),
+                Err(HeroError::NotNFTOwner.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_cooldown() {
+        let config = GalleryConfig {
+            cooldown_seconds: 60,
+            ..GalleryConfig::default()
+        };
+        let mut nft_record = NFTRecord::from(legacy_record(0));
+        assert_eq!(Processor::verify_cooldown(&nft_record, &config, 0), Ok(()));
+
+        nft_record.last_sold_at = 100;
+        for now in [100, 159].iter() {
+            assert_eq!(
+                Processor::verify_cooldown(&nft_record, &config, *now),
+                Err(HeroError::SeatCoolingDown.into())
+            );
+        }
+        assert_eq!(Processor::verify_cooldown(&nft_record, &config, 160), Ok(()));
+    }
+
+    #[test]
+    fn test_moderate_content_keeps_content() {
+        let program_id = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let mut admin = TestAccount::new(admin_key, system_program::id(), vec![]);
+        let mut repository = repository_account(&program_id, &admin_key);
+        let accounts = [admin.info(true), repository.info(false)];
+        Processor::save_nft_data_to_repository(&NFTRecord::from(legacy_record(0)), accounts[1].clone()).unwrap();
+        let moderate = |moderation, placeholder_uri: Option<&str>| {
+            let args = ModerateContentArgs {
+                hero_id: 0,
+                moderation,
+                placeholder_uri: placeholder_uri.map(|uri| uri.to_string())
+            };
+            Processor::process(&program_id, &accounts, &HeroInstruction::ModerateContent(args).pack())
+        };
+
+        // a seat without placeholder can't be hidden, its content would still be shown
+        for placeholder_uri in [None, Some("")].iter() {
+            assert_eq!(moderate(ModerationStatus::Hidden, *placeholder_uri), Err(HeroError::InvalidConfig.into()));
+        }
+        let nft_record = Processor::read_nft_record(0, accounts[1].clone()).unwrap();
+        assert_eq!(nft_record.moderation, ModerationStatus::Visible);
+
+        moderate(ModerationStatus::Hidden, Some("ar://placeholder")).unwrap();
+        let nft_record = Processor::read_nft_record(0, accounts[1].clone()).unwrap();
+        assert_eq!(nft_record.content_uri, "https://arweave.net/hero");
+        assert_eq!(nft_record.display_uri(), "ar://placeholder");
+
+        // hiding again keeps the placeholder
+        moderate(ModerationStatus::Hidden, None).unwrap();
+        assert_eq!(Processor::read_nft_record(0, accounts[1].clone()).unwrap().display_uri(), "ar://placeholder");
+
+        assert_eq!(
+            moderate(ModerationStatus::Visible, Some("ar://placeholder")),
+            Err(HeroError::InvalidInstruction.into())
+        );
+        moderate(ModerationStatus::Visible, None).unwrap();
+        let nft_record = Processor::read_nft_record(0, accounts[1].clone()).unwrap();
+        assert_eq!(nft_record.placeholder_uri, "");
+        assert_eq!(nft_record.display_uri(), "https://arweave.net/hero");
+    }
+
+    #[test]
+    fn test_exchange_seats() {
+        let mut nft_record_a = NFTRecord::from(legacy_record(0));
+        nft_record_a.content_hash = Some([1; 32]);
+        nft_record_a.caption = "a".to_string();
+        nft_record_a.link = "https://a.example".to_string();
+        nft_record_a.accent_color = Some([1; 3]);
+        nft_record_a.sale_count = 3;
+        nft_record_a.last_sold_at = 100;
+        let mut nft_record_b = NFTRecord::from(legacy_record(1));
+        nft_record_b.moderation = ModerationStatus::Hidden;
+        nft_record_b.placeholder_uri = "ar://placeholder".to_string();
+        nft_record_b.listed_price = 5;
+        let (a, b) = (nft_record_a.clone(), nft_record_b.clone());
+
+        Processor::exchange_seats(&mut nft_record_a, &mut nft_record_b);
+        for (seat, held, other) in [(&nft_record_a, &b, &a), (&nft_record_b, &a, &b)].iter() {
+            assert_eq!(
+                **seat,
+                NFTRecord {
+                    hero_id: other.hero_id,
+                    last_price: other.last_price,
+                    sale_count: other.sale_count,
+                    last_sold_at: other.last_sold_at,
+                    ..(*held).clone()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_update_content_keeps_unset_fields() {
+        let program_id = Pubkey::new_unique();
+        let owner_key = Pubkey::new_unique();
+        let mut owner = TestAccount::new(owner_key, system_program::id(), vec![]);
+        let mut seat = occupied_seat(&program_id, &Pubkey::new_unique(), &owner_key);
+        let accounts = [owner.info(true), seat.repository.info(false), seat.nft.info(false), seat.token.info(false)];
+        let update = |args: UpdateContentArgs| {
+            Processor::process(&program_id, &accounts, &HeroInstruction::UpdateContent(args).pack())
+        };
+        let unchanged = UpdateContentArgs {
+            hero_id: 0,
+            key_nft: seat.nft_record.key_nft,
+            content_uri: None,
+            content_hash: None,
+            caption: None,
+            link: None,
+            accent_color: None
+        };
+
+        update(UpdateContentArgs {
+            content_hash: Some(Some([1; 32])),
+            caption: Some("Hero".to_string()),
+            ..unchanged.clone()
+        }).unwrap();
+        let nft_record = Processor::read_nft_record(0, accounts[1].clone()).unwrap();
+        assert_eq!(nft_record, NFTRecord {
+            content_hash: Some([1; 32]),
+            caption: "Hero".to_string(),
+            ..seat.nft_record.clone()
+        });
+
+        // the hash was of the replaced content
+        update(UpdateContentArgs {
+            content_uri: Some("ar://new".to_string()),
+            ..unchanged.clone()
+        }).unwrap();
+        let nft_record = Processor::read_nft_record(0, accounts[1].clone()).unwrap();
+        assert_eq!(nft_record, NFTRecord {
+            content_uri: "ar://new".to_string(),
+            caption: "Hero".to_string(),
+            ..seat.nft_record.clone()
+        });
+
+        assert_eq!(
+            update(UpdateContentArgs {
+                caption: Some("c".repeat(MAX_CAPTION_LENGTH + 1)),
+                ..unchanged
+            }),
+            Err(HeroError::InvalidContent.into())
+        );
+    }
+
+    #[test]
+    fn test_update_record_rejects_empty_content_uri() {
+        let program_id = Pubkey::new_unique();
+        let owner_key = Pubkey::new_unique();
+        let mut owner = TestAccount::new(owner_key, system_program::id(), vec![]);
+        let mut seat = occupied_seat(&program_id, &Pubkey::new_unique(), &owner_key);
+        let args = UpdateRecordArgs {
+            hero_id: 0,
+            key_nft: seat.nft_record.key_nft,
+            new_price: seat.nft_record.listed_price,
+            content_uri: String::new(),
+            content_hash: None
+        };
+        let accounts = [owner.info(true), seat.repository.info(false), seat.nft.info(false), seat.token.info(false)];
+        assert_eq!(
+            Processor::process(&program_id, &accounts, &HeroInstruction::UpdateRecord(args).pack()),
+            Err(HeroError::InvalidUri.into())
+        );
+    }
+
+    #[test]
+    fn test_verify_seat_setter() {
+        let program_id = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let owner_key = Pubkey::new_unique();
+        let mut seat = occupied_seat(&program_id, &admin_key, &owner_key);
+        let key_nft = seat.nft_record.key_nft;
+        let verify = |setter_key: Pubkey, seat: &mut Seat| {
+            let mut setter = TestAccount::new(setter_key, system_program::id(), vec![]);
+            Processor::verify_seat_setter(
+                &setter.info(true),
+                &seat.repository.info(false),
+                &seat.nft.info(false),
+                &seat.token.info(false),
+                0,
+                &key_nft,
+                &program_id
+            )
+        };
+
+        // non admin owner
+        assert_eq!(verify(owner_key, &mut seat), Ok(seat.nft_record.clone()));
+        // neither owner nor admin
+        assert_eq!(verify(Pubkey::new_unique(), &mut seat), Err(HeroError::NotNFTOwner.into()));
+        // admin overriding the owner
+        assert_eq!(verify(admin_key, &mut seat), Ok(seat.nft_record.clone()));
+
+        // token account no longer holding the nft
+        seat.token = token_test_account(token_account(&key_nft, &owner_key, 0));
+        assert_eq!(verify(owner_key, &mut seat), Err(HeroError::NotNFTOwner.into()));
+    }
+
+    #[test]
+    fn test_validate_new_record_occupied_seat() {
+        let program_id = Pubkey::new_unique();
+        let mut seat = occupied_seat(&program_id, &Pubkey::new_unique(), &Pubkey::new_unique());
+        let mut mint = mint_test_account(0, 1);
+        let key_nft = mint.key;
+        let mut args = AddRecordArgs {
+            hero_id: 0,
+            content_uri: "ar://hero".to_string(),
+            key_nft,
+            last_price: 0,
+            listed_price: 1,
+            force: false,
+            content_hash: None,
+            caption: String::new(),
+            link: String::new(),
+            accent_color: None
+        };
+        let mint_info = mint.info(false);
+        let repository = seat.repository.info(false);
+
+        assert_eq!(
+            Processor::validate_new_record(&args, &mint_info, repository.clone()),
+            Err(HeroError::SeatOccupied.into())
+        );
+        args.force = true;
+        assert_eq!(
+            Processor::validate_new_record(&args, &mint_info, repository.clone()).map(|nft_record| nft_record.key_nft),
+            Ok(key_nft)
+        );
+        args.hero_id = 1;
+        args.force = false;
+        assert!(Processor::validate_new_record(&args, &mint_info, repository).is_ok());
+    }
+
+    #[test]
+    fn test_remove_record() {
+        let program_id = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let mut admin = TestAccount::new(admin_key, system_program::id(), vec![]);
+        let mut stranger = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        let mut seat = occupied_seat(&program_id, &admin_key, &Pubkey::new_unique());
+        let repository = seat.repository.info(false);
+        let remove = HeroInstruction::RemoveRecord(RemoveRecordArgs { hero_id: 0 }).pack();
+
+        assert_eq!(
+            Processor::process(&program_id, &[stranger.info(true), repository.clone()], &remove),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        Processor::process(&program_id, &[admin.info(true), repository.clone()], &remove).unwrap();
+        assert!(Processor::read_nft_record(0, repository).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_verify_nft_mint() {
+        assert_eq!(Processor::verify_nft_mint(&mint_test_account(0, 1).info(false)), Ok(()));
+        for mint in [mint_test_account(2, 1), mint_test_account(0, 2), mint_test_account(0, 0)].iter_mut() {
+            assert_eq!(Processor::verify_nft_mint(&mint.info(false)), Err(HeroError::InvalidMint.into()));
+        }
+
+        let mut foreign = mint_test_account(0, 1);
+        foreign.owner = Pubkey::new_unique();
+        assert_eq!(Processor::verify_nft_mint(&foreign.info(false)), Err(HeroError::InvalidMint.into()));
+        let mut token = token_test_account(token_account(&Pubkey::new_unique(), &Pubkey::new_unique(), 1));
+        assert_eq!(Processor::verify_nft_mint(&token.info(false)), Err(HeroError::InvalidMint.into()));
+    }
+
+    #[test]
+    fn test_verify_nft_key_unique() {
+        let program_id = Pubkey::new_unique();
+        let mut seat = occupied_seat(&program_id, &Pubkey::new_unique(), &Pubkey::new_unique());
+        let key_nft = seat.nft_record.key_nft;
+        let repository = seat.repository.info(false);
+
+        // the seat may keep its own nft
+        assert_eq!(Processor::verify_nft_key_unique(0, &key_nft, repository.clone()), Ok(()));
+        assert_eq!(
+            Processor::verify_nft_key_unique(1, &key_nft, repository.clone()),
+            Err(HeroError::DuplicateNFTKey.into())
+        );
+        assert_eq!(Processor::verify_nft_key_unique(1, &Pubkey::new_unique(), repository), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_new_seat_nft() {
+        let program_id = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let mut seat = occupied_seat(&program_id, &admin, &Pubkey::new_unique());
+        let mut new_mint = mint_test_account(0, 1);
+        let mut send = token_test_account(token_account(&new_mint.key, &admin, 1));
+        let mut fungible_mint = mint_test_account(2, 1);
+        let mut fungible_send = token_test_account(token_account(&fungible_mint.key, &admin, 1));
+        // seat 1 already holds the other nft
+        let mut other_mint = mint_test_account(0, 1);
+        let mut other_send = token_test_account(token_account(&other_mint.key, &admin, 1));
+        let other_record = NFTRecord { key_nft: other_mint.key, ..seat.nft_record.clone() };
+        other_record.serialize(&mut &mut seat.repository.data[NFT_RECORD_SIZE..2 * NFT_RECORD_SIZE]).unwrap();
+        let repository = seat.repository.info(false);
+
+        assert_eq!(
+            Processor::verify_new_seat_nft(0, &new_mint.info(false), &send.info(false), &repository),
+            Ok(())
+        );
+        assert_eq!(
+            Processor::verify_new_seat_nft(0, &fungible_mint.info(false), &fungible_send.info(false), &repository),
+            Err(HeroError::InvalidMint.into())
+        );
+        assert_eq!(
+            Processor::verify_new_seat_nft(0, &other_mint.info(false), &other_send.info(false), &repository),
+            Err(HeroError::DuplicateNFTKey.into())
+        );
+        assert_eq!(
+            Processor::verify_new_seat_nft(0, &new_mint.info(false), &other_send.info(false), &repository),
+            Err(HeroError::InvalidTokenAccount.into())
+        );
+    }
+
+    #[test]
+    fn test_read_seat_errors() {
+        let program_id = Pubkey::new_unique();
+        let mut seat = occupied_seat(&program_id, &Pubkey::new_unique(), &Pubkey::new_unique());
+        let key_nft = seat.nft_record.key_nft;
+        let nft = seat.nft.info(false);
+        let repository = seat.repository.info(false);
+
+        assert_eq!(Processor::record_range(NFT_COUNT as u8), Err(HeroError::HeroOverflow.into()));
+        assert_eq!(
+            Processor::read_nft_record(NFT_COUNT as u8, repository.clone()),
+            Err(HeroError::HeroOverflow.into())
+        );
+        assert_eq!(
+            Processor::get_nft_data_from_repository(1, &key_nft, repository.clone(), nft.clone()),
+            Err(HeroError::SeatEmpty.into())
+        );
+        assert_eq!(
+            Processor::get_nft_data_from_repository(0, &Pubkey::new_unique(), repository.clone(), nft.clone()),
+            Err(HeroError::InvalidNFTKey.into())
+        );
+        assert_eq!(
+            Processor::get_nft_data_from_repository(0, &key_nft, repository, nft).map(|nft_record| nft_record.key_nft),
+            Ok(key_nft)
+        );
+
+        let mut unallocated = TestAccount::new(Pubkey::new_unique(), program_id, vec![]);
+        assert_eq!(
+            Processor::read_nft_record(0, unallocated.info(false)),
+            Err(HeroError::UninitializedRepository.into())
+        );
+    }
+
+    #[test]
+    fn test_verify_program_accounts() {
+        let mut token_program = TestAccount::program(spl_token::id());
+        assert_eq!(
+            Processor::verify_program_account(&token_program.info(false), &spl_token::id(), HeroError::InvalidTokenProgram),
+            Ok(())
+        );
+        let mut impostor = TestAccount::program(Pubkey::new_unique());
+        assert_eq!(
+            Processor::verify_program_account(&impostor.info(false), &spl_token::id(), HeroError::InvalidTokenProgram),
+            Err(HeroError::InvalidTokenProgram.into())
+        );
+        let mut not_executable = TestAccount::new(spl_token::id(), Pubkey::new_unique(), vec![]);
+        assert_eq!(
+            Processor::verify_program_account(&not_executable.info(false), &spl_token::id(), HeroError::InvalidTokenProgram),
+            Err(HeroError::InvalidTokenProgram.into())
+        );
+
+        let mut token = token_test_account(token_account(&Pubkey::new_unique(), &Pubkey::new_unique(), 1));
+        assert_eq!(Processor::verify_token_program_owned(&token.info(false)), Ok(()));
+        token.owner = Pubkey::new_unique();
+        assert_eq!(
+            Processor::verify_token_program_owned(&token.info(false)),
+            Err(HeroError::InvalidTokenAccountOwner.into())
+        );
+    }
+
+    #[test]
+    fn test_create_associated_token_account_address() {
+        let mut buyer = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        let mut mint = mint_test_account(0, 1);
+        let mut other_account = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        let mut system_program_account = TestAccount::program(system_program::id());
+        let mut token_program = TestAccount::program(spl_token::id());
+        let mut rent_sysvar = TestAccount::new(solana_program::sysvar::rent::id(), Pubkey::new_unique(), vec![]);
+        let mut associated_token_program = TestAccount::program(spl_associated_token_account::id());
+        let buyer = buyer.info(true);
+
+        // refused before creating anything
+        assert_eq!(
+            Processor::create_associated_token_account(
+                buyer.clone(),
+                buyer,
+                other_account.info(false),
+                mint.info(false),
+                system_program_account.info(false),
+                token_program.info(false),
+                rent_sysvar.info(false),
+                associated_token_program.info(false)
+            ),
+            Err(HeroError::InvalidTokenAccount.into())
+        );
+    }
+
+    #[test]
+    fn test_verify_buy_accounts() {
+        let program_id = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let mut admin = TestAccount::new(admin_key, system_program::id(), vec![]);
+        let mut buyer = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        let mut repository = repository_account(&program_id, &admin_key);
+        let repository_key = repository.key;
+        let mut token_program = TestAccount::program(spl_token::id());
+        let mut token_metadata_program = TestAccount::program(spl_token_metadata::id());
+        let mut system_program_account = TestAccount::program(system_program::id());
+        let (history_address, _) = find_history_address(&repository_key, &program_id);
+        let mut history = TestAccount::new(history_address, system_program::id(), vec![]);
+        let mut graveyard = graveyard_account(&program_id, &repository_key, 1);
+        let (gallery_authority_address, _) = find_gallery_authority_address(&repository_key, &program_id);
+        let mut gallery_authority = TestAccount::new(gallery_authority_address, system_program::id(), vec![]);
+
+        let admin = admin.info(false);
+        let buyer = buyer.info(true);
+        let repository = repository.info(false);
+        let token_program = token_program.info(false);
+        let token_metadata_program = token_metadata_program.info(false);
+        let system_program_account = system_program_account.info(false);
+        let history = history.info(false);
+        let graveyard = graveyard.info(false);
+        let gallery_authority = gallery_authority.info(false);
+        let buy_accounts = BuyAccounts {
+            admin_account: &admin,
+            buyer_account: &buyer,
+            repository_account: &repository,
+            token_program: &token_program,
+            token_metadata_program: &token_metadata_program,
+            system_program_account: &system_program_account,
+            history_account: &history,
+            graveyard_account: &graveyard,
+            gallery_authority: &gallery_authority,
+            associated_token_program: None,
+            rent_sysvar: None
+        };
+        assert_eq!(Processor::verify_buy_accounts(&buy_accounts, &program_id), Ok(()));
+
+        let swapped_programs = BuyAccounts {
+            token_program: &system_program_account,
+            system_program_account: &token_program,
+            ..buy_accounts
+        };
+        assert_eq!(
+            Processor::verify_buy_accounts(&swapped_programs, &program_id),
+            Err(HeroError::InvalidTokenProgram.into())
+        );
+        let other_graveyard = BuyAccounts { graveyard_account: &history, ..buy_accounts };
+        assert_eq!(
+            Processor::verify_buy_accounts(&other_graveyard, &program_id),
+            Err(HeroError::InvalidGraveyardAccount.into())
+        );
+        let not_admin = BuyAccounts { admin_account: &buyer, ..buy_accounts };
+        assert_eq!(
+            Processor::verify_buy_accounts(&not_admin, &program_id),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        let unsigned = BuyAccounts { buyer_account: &admin, ..buy_accounts };
+        assert_eq!(
+            Processor::verify_buy_accounts(&unsigned, &program_id),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+    }
+
+    #[test]
+    fn test_swap_seats_checks() {
+        let program_id = Pubkey::new_unique();
+        let owner_a_key = Pubkey::new_unique();
+        let owner_b_key = Pubkey::new_unique();
+        let mut owner_a = TestAccount::new(owner_a_key, system_program::id(), vec![]);
+        let mut owner_b = TestAccount::new(owner_b_key, system_program::id(), vec![]);
+        let mut seat = occupied_seat(&program_id, &Pubkey::new_unique(), &owner_a_key);
+        let nft_record_b = NFTRecord::from(legacy_record(1));
+        nft_record_b.serialize(&mut &mut seat.repository.data[NFT_RECORD_SIZE..2 * NFT_RECORD_SIZE]).unwrap();
+        let mut nft_b = TestAccount::new(nft_record_b.key_nft, spl_token::id(), vec![]);
+        // seat b's nft is held by someone else than owner b
+        let mut token_b = token_test_account(token_account(&nft_record_b.key_nft, &Pubkey::new_unique(), 1));
+        let (history_address, _) = find_history_address(&seat.repository.key, &program_id);
+        let mut history = TestAccount::new(history_address, system_program::id(), vec![]);
+        let accounts = [
+            owner_a.info(true),
+            owner_b.info(true),
+            seat.repository.info(false),
+            seat.nft.info(false),
+            seat.token.info(false),
+            nft_b.info(false),
+            token_b.info(false),
+            history.info(false),
+        ];
+        let swap = |hero_id_b| {
+            let args = SwapSeatsArgs { hero_id_a: 0, hero_id_b, lamports_a_to_b: 0, lamports_b_to_a: 0 };
+            Processor::process(&program_id, &accounts, &HeroInstruction::SwapSeats(args).pack())
+        };
+
+        assert_eq!(swap(0), Err(HeroError::InvalidInstruction.into()));
+        assert_eq!(swap(1), Err(HeroError::NotNFTOwner.into()));
+    }
+
+    #[test]
+    fn test_transfer_seat_checks() {
+        let program_id = Pubkey::new_unique();
+        let owner_key = Pubkey::new_unique();
+        let mut owner = TestAccount::new(owner_key, system_program::id(), vec![]);
+        let mut stranger = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        let mut recipient = TestAccount::new(Pubkey::new_unique(), system_program::id(), vec![]);
+        let mut seat = occupied_seat(&program_id, &Pubkey::new_unique(), &owner_key);
+        // an existing token account of the nft, but not the recipient's
+        let mut other_token = token_test_account(token_account(&seat.nft_record.key_nft, &Pubkey::new_unique(), 0));
+        let mut token_program = TestAccount::program(spl_token::id());
+        let (history_address, _) = find_history_address(&seat.repository.key, &program_id);
+        let mut history = TestAccount::new(history_address, system_program::id(), vec![]);
+        let transfer = HeroInstruction::TransferSeat(TransferSeatArgs { hero_id: 0, new_listed_price: None }).pack();
+
+        let mut accounts = vec![
+            stranger.info(true),
+            recipient.info(false),
+            seat.repository.info(false),
+            seat.nft.info(false),
+            seat.token.info(false),
+            other_token.info(false),
+            token_program.info(false),
+            history.info(false),
+        ];
+        assert_eq!(
+            Processor::process(&program_id, &accounts, &transfer),
+            Err(HeroError::NotNFTOwner.into())
+        );
+        accounts[0] = owner.info(true);
+        assert_eq!(
+            Processor::process(&program_id, &accounts, &transfer),
+            Err(HeroError::InvalidTokenAccount.into())
+        );
+    }
+}
@@ -1,18 +1,311 @@
 use solana_program::{
+    entrypoint::MAX_PERMITTED_DATA_INCREASE,
+    program_error::ProgramError,
     pubkey::Pubkey
-
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 
 pub const NFT_COUNT: usize = 12;
-pub const NFT_RECORD_SIZE: usize = 250; // 133
+pub const NFT_RECORD_SIZE: usize = 768; // 707
+/// bounds of the display fields of a seat, in bytes
+pub const MAX_CONTENT_URI_LENGTH: usize = 200;
+pub const MAX_CAPTION_LENGTH: usize = 64;
+pub const MAX_LINK_LENGTH: usize = 128;
+/// content_uri prefixes allowed while the gallery config doesn't list any
+pub const DEFAULT_ALLOWED_URI_PREFIXES: &[&str] = &["https://", "ar://", "ipfs://"];
 pub const REPO_ACCOUNT_SEED: &str = "hallofheros";
+/// gallery config follows the seats in repository account
+pub const CONFIG_OFFSET: usize = NFT_COUNT * NFT_RECORD_SIZE;
+pub const GALLERY_CONFIG_SIZE: usize = 1024;
+/// at most `MAX_PERMITTED_DATA_INCREASE`, `MigrateRepository` creates an account of this size
+pub const REPOSITORY_SIZE: usize = CONFIG_OFFSET + GALLERY_CONFIG_SIZE;
+/// seat size of repositories created before the gallery config, see `MigrateRepository`
+pub const LEGACY_NFT_RECORD_SIZE: usize = 250;
+pub const MIGRATION_SEED: &[u8] = b"migration";
+
+pub const HISTORY_SEED: &[u8] = b"history";
+/// sales kept per seat
+pub const HISTORY_LENGTH: usize = 8;
+pub const PROVENANCE_ENTRY_SIZE: usize = 89;
+/// entry count, next entry index and the entries
+pub const SEAT_HISTORY_SIZE: usize = 2 + HISTORY_LENGTH * PROVENANCE_ENTRY_SIZE;
+pub const HISTORY_SIZE: usize = NFT_COUNT * SEAT_HISTORY_SIZE;
+
+pub const GALLERY_AUTHORITY_SEED: &[u8] = b"gallery";
+
+pub const GRAVEYARD_SEED: &[u8] = b"graveyard";
+/// number of dead nfts and page capacity
+pub const GRAVEYARD_SIZE: usize = 6;
+pub const DEAD_NFT_SIZE: usize = 81;
+/// graveyard pages are created by the program, which can't allocate more in one instruction
+pub const MAX_GRAVEYARD_CAPACITY: usize = MAX_PERMITTED_DATA_INCREASE / DEAD_NFT_SIZE;
+
+/// Program derived address of the history account of a repository
+pub fn find_history_address(repository: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[HISTORY_SEED, repository.as_ref()], program_id)
+}
+
+/// Program derived address holding the seats of a repository while it is migrated
+pub fn find_migration_address(repository: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MIGRATION_SEED, repository.as_ref()], program_id)
+}
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+/// Program derived address of the graveyard account of a repository
+pub fn find_graveyard_address(repository: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GRAVEYARD_SEED, repository.as_ref()], program_id)
+}
+
+/// Program derived address of a graveyard page, listing dead nfts once the previous pages are full
+pub fn find_graveyard_page_address(repository: &Pubkey, page: u32, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GRAVEYARD_SEED, repository.as_ref(), &page.to_le_bytes()], program_id)
+}
+
+/// Program derived address holding update authority of seat nft metadata
+pub fn find_gallery_authority_address(repository: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[GALLERY_AUTHORITY_SEED, repository.as_ref()], program_id)
+}
+
+/// Token metadata account of a seat nft mint
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            spl_token_metadata::state::PREFIX.as_bytes(),
+            spl_token_metadata::id().as_ref(),
+            mint.as_ref(),
+        ],
+        &spl_token_metadata::id(),
+    )
+}
+
+/// A seat of the hall. A zeroed slot in the repository account is an empty seat.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct NFTRecord{
+    pub hero_id: u8,
+    pub content_uri: String,
+    pub key_nft: Pubkey,
+    pub last_price: u64,
+    pub listed_price: u64,
+    pub sale_count: u32,
+    pub moderation: ModerationStatus,
+    /// hash of the asset at content_uri the holder committed to, e.g. SHA-256
+    pub content_hash: Option<[u8; 32]>,
+    /// short text shown with the image
+    pub caption: String,
+    /// external https link of the hero
+    pub link: String,
+    /// rgb color frontends may frame the seat with
+    pub accent_color: Option<[u8; 3]>,
+    /// unix timestamp of the last sale, 0 if never sold
+    pub last_sold_at: i64,
+    /// shown instead of content_uri while the seat is hidden, empty for none
+    pub placeholder_uri: String
+}
+
+/// Set by curators with `ModerateContent`, frontends render a placeholder for hidden seats
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "client", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModerationStatus {
+    Visible,
+    Hidden
+}
+
+impl Default for ModerationStatus {
+    fn default() -> Self {
+        ModerationStatus::Visible
+    }
+}
+
+impl NFTRecord {
+    pub fn is_empty(&self) -> bool {
+        self.key_nft == Pubkey::default()
+    }
+
+    /// uri to show for the seat, its placeholder while hidden
+    pub fn display_uri(&self) -> &str {
+        if self.moderation == ModerationStatus::Hidden && !self.placeholder_uri.is_empty() {
+            &self.placeholder_uri
+        } else {
+            &self.content_uri
+        }
+    }
+}
+
+/// A seat of a repository created before the gallery config, in `LEGACY_NFT_RECORD_SIZE` slots
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct LegacyNFTRecord {
     pub hero_id: u8,
     pub content_uri: String,
     pub key_nft: Pubkey,
     pub last_price: u64,
     pub listed_price: u64
 }
+
+impl From<LegacyNFTRecord> for NFTRecord {
+    fn from(legacy_record: LegacyNFTRecord) -> Self {
+        Self {
+            hero_id: legacy_record.hero_id,
+            content_uri: legacy_record.content_uri,
+            key_nft: legacy_record.key_nft,
+            last_price: legacy_record.last_price,
+            listed_price: legacy_record.listed_price,
+            sale_count: 0,
+            moderation: ModerationStatus::Visible,
+            content_hash: None,
+            caption: String::new(),
+            link: String::new(),
+            accent_color: None,
+            last_sold_at: 0,
+            placeholder_uri: String::new()
+        }
+    }
+}
+
+/// Settings of the gallery, stored at `CONFIG_OFFSET` of repository account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "client", derive(serde::Serialize, serde::Deserialize))]
+pub struct GalleryConfig {
+    pub is_initialized: bool,
+    /// name of a retired seat nft, `{hero_id}` and `{sale_count}` are replaced
+    pub dead_name_template: String,
+    /// uri of a retired seat nft is `{dead_uri_base}{hero_id}-{sale_count}.json`
+    pub dead_uri_base: String,
+    /// content_uri of a seat must start with one of these, e.g. `ar://`. `DEFAULT_ALLOWED_URI_PREFIXES` when empty.
+    pub allowed_uri_prefixes: Vec<String>,
+    /// seconds after a sale during which the seat can't be sold, swapped or repriced, 0 for none
+    pub cooldown_seconds: u64
+}
+
+impl GalleryConfig {
+    pub fn dead_name(&self, hero_id: u8, sale_count: u32) -> String {
+        self.dead_name_template
+            .replace("{hero_id}", &hero_id.to_string())
+            .replace("{sale_count}", &sale_count.to_string())
+    }
+
+    pub fn dead_uri(&self, hero_id: u8, sale_count: u32) -> String {
+        format!("{}{}-{}.json", self.dead_uri_base, hero_id, sale_count)
+    }
+
+    /// end of the cooldown of a seat sold at `last_sold_at`
+    pub fn cooldown_ends_at(&self, last_sold_at: i64) -> i64 {
+        if last_sold_at == 0 {
+            return 0;
+        }
+        last_sold_at.saturating_add(self.cooldown_seconds.min(i64::MAX as u64) as i64)
+    }
+
+    /// an empty uri leaves the seat without content
+    pub fn is_allowed_uri(&self, uri: &str) -> bool {
+        if uri.is_empty() {
+            return true;
+        }
+        if uri.chars().any(char::is_control) {
+            return false;
+        }
+        if self.allowed_uri_prefixes.is_empty() {
+            return DEFAULT_ALLOWED_URI_PREFIXES.iter().any(|prefix| uri.starts_with(prefix));
+        }
+        self.allowed_uri_prefixes.iter().any(|prefix| uri.starts_with(prefix.as_str()))
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ProvenanceKind {
+    Sale,
+    Transfer,
+    Swap
+}
+
+/// A change of holder of a seat
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ProvenanceEntry {
+    pub kind: ProvenanceKind,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub price: u64,
+    pub slot: u64,
+    pub timestamp: i64
+}
+
+/// Ring buffer of the last `HISTORY_LENGTH` entries of a seat, oldest first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SeatHistory {
+    pub entries: Vec<ProvenanceEntry>
+}
+
+impl SeatHistory {
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < SEAT_HISTORY_SIZE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let count = (data[0] as usize).min(HISTORY_LENGTH);
+        let next = data[1] as usize % HISTORY_LENGTH;
+        // when full, the oldest entry is the one about to be overwritten
+        let first = if count == HISTORY_LENGTH { next } else { 0 };
+        let mut entries = Vec::with_capacity(count);
+        for i in 0..count {
+            let start = 2 + (first + i) % HISTORY_LENGTH * PROVENANCE_ENTRY_SIZE;
+            entries.push(ProvenanceEntry::deserialize(&mut &data[start..start + PROVENANCE_ENTRY_SIZE])?);
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        if data.len() < SEAT_HISTORY_SIZE || self.entries.len() > HISTORY_LENGTH {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        data[0] = self.entries.len() as u8;
+        data[1] = (self.entries.len() % HISTORY_LENGTH) as u8;
+        for (i, entry) in self.entries.iter().enumerate() {
+            let start = 2 + i * PROVENANCE_ENTRY_SIZE;
+            entry.serialize(&mut &mut data[start..start + PROVENANCE_ENTRY_SIZE])?;
+        }
+        Ok(())
+    }
+
+    /// append an entry, dropping the oldest one when full
+    pub fn push(&mut self, entry: ProvenanceEntry) {
+        if self.entries.len() == HISTORY_LENGTH {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry);
+    }
+}
+
+/// A seat nft retired by a sale
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct DeadNFT {
+    pub mint: Pubkey,
+    pub hero_id: u8,
+    pub former_owner: Pubkey,
+    pub sale_price: u64,
+    pub died_at: i64
+}
+
+/// Graveyard account of a gallery. Dead nfts are appended to page accounts of `page_capacity`
+/// each, so every seat nft retired by a sale is kept
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GraveyardIndex {
+    /// seat nfts buried so far
+    pub buried: u32,
+    pub page_capacity: u16
+}
+
+impl GraveyardIndex {
+    /// page and slot in the page of the `burial`th dead nft, counting from 0
+    pub fn locate(&self, burial: u32) -> (u32, usize) {
+        let page_capacity = self.page_capacity as u32;
+        (burial / page_capacity, (burial % page_capacity) as usize)
+    }
+
+    /// number of pages holding dead nfts
+    pub fn page_count(&self) -> u32 {
+        let (page, slot) = self.locate(self.buried);
+        if slot == 0 { page } else { page + 1 }
+    }
+
+    /// size of a page account
+    pub fn page_size(&self) -> usize {
+        self.page_capacity as usize * DEAD_NFT_SIZE
+    }
+}
@@ -0,0 +1,256 @@
+#![cfg(feature = "client")]
+
+use {
+    borsh::BorshSerialize,
+    hall_of_heros::{
+        client::{
+            decode_accent_color, decode_content_hash, encode_content_hash, Gallery,
+            GalleryJson, Graveyard, History, NFTRecordJson,
+        },
+        error::HeroError,
+        instruction::HeroInstruction,
+        processor::ModerateContentArgs,
+        state::{
+            DeadNFT, GalleryConfig, GraveyardIndex, ModerationStatus, NFTRecord, ProvenanceEntry, ProvenanceKind, SeatHistory,
+            DEAD_NFT_SIZE, HISTORY_LENGTH, LEGACY_NFT_RECORD_SIZE, HISTORY_SIZE, MAX_CAPTION_LENGTH, MAX_CONTENT_URI_LENGTH,
+            MAX_LINK_LENGTH, NFT_COUNT, NFT_RECORD_SIZE, REPOSITORY_SIZE, SEAT_HISTORY_SIZE,
+        },
+    },
+    solana_program::pubkey::Pubkey,
+};
+
+#[test]
+fn test_gallery_unpack_and_quote() {
+    let nft_record = NFTRecord {
+        hero_id: 2,
+        content_uri: "https://arweave.net/hero".to_string(),
+        key_nft: Pubkey::new_unique(),
+        last_price: 10,
+        listed_price: 20,
+        sale_count: 0,
+        moderation: ModerationStatus::Visible,
+        content_hash: None,
+        caption: String::new(),
+        link: String::new(),
+        accent_color: None,
+        last_sold_at: 0,
+        placeholder_uri: String::new(),
+    };
+    let mut data = vec![0; REPOSITORY_SIZE];
+    let start = 2 * NFT_RECORD_SIZE;
+    nft_record
+        .serialize(&mut &mut data[start..start + NFT_RECORD_SIZE])
+        .unwrap();
+
+    let gallery = Gallery::unpack(&data).unwrap();
+    assert_eq!(gallery.seats.len(), NFT_COUNT);
+    assert_eq!(gallery.seat(2), Some(&nft_record));
+    assert_eq!(gallery.seat(0), None);
+    assert_eq!(gallery.config, None);
+
+    let quote = gallery.quote(2, 5000, Some(100)).unwrap();
+    assert_eq!(quote.price, 20);
+    assert_eq!(quote.fees, 10_100);
+    assert_eq!(quote.total, 10_120);
+    assert!(gallery.quote(0, 5000, None).is_err());
+    assert!(gallery.quote(2, u64::MAX, None).is_err());
+    assert!(gallery.quote(2, 5000, Some(u64::MAX - 10_010)).is_err());
+}
+
+#[test]
+fn test_gallery_unpack_legacy_layout() {
+    assert_eq!(
+        Gallery::unpack(&[0; LEGACY_NFT_RECORD_SIZE * NFT_COUNT]),
+        Err(HeroError::RepositoryNotMigrated.into())
+    );
+    assert_eq!(Gallery::unpack(&[]), Err(HeroError::UninitializedRepository.into()));
+}
+
+#[test]
+fn test_gallery_json_round_trip() {
+    let nft_record = NFTRecord {
+        hero_id: 1,
+        content_uri: "ar://hero".to_string(),
+        key_nft: Pubkey::new_unique(),
+        last_price: 1,
+        listed_price: 2,
+        sale_count: 0,
+        moderation: ModerationStatus::Hidden,
+        content_hash: Some([0xab; 32]),
+        caption: "Hero".to_string(),
+        link: "https://example.com".to_string(),
+        accent_color: Some([0x12, 0x34, 0x56]),
+        last_sold_at: 1_650_000_000,
+        placeholder_uri: "ar://placeholder".to_string(),
+    };
+    let mut seats = vec![None; NFT_COUNT];
+    seats[1] = Some(nft_record.clone());
+    let gallery = Gallery {
+        seats,
+        config: None,
+    };
+
+    let json = gallery
+        .to_json(&Pubkey::new_unique())
+        .to_string_pretty()
+        .unwrap();
+    let gallery_json = GalleryJson::parse(&json).unwrap();
+    assert_eq!(gallery_json.records().unwrap(), vec![nft_record]);
+
+    let program_id = Pubkey::new_unique();
+    let transactions = gallery_json
+        .import_transactions(&program_id, &Pubkey::new_unique(), false, |_| true)
+        .unwrap();
+    // AddRecords then ModerateContent for the hidden seat
+    assert_eq!(transactions.len(), 1);
+    let instructions = &transactions[0];
+    assert_eq!(instructions.len(), 2);
+    assert_eq!(
+        HeroInstruction::unpack(&instructions[1].data).unwrap(),
+        HeroInstruction::ModerateContent(ModerateContentArgs {
+            hero_id: 1,
+            moderation: ModerationStatus::Hidden,
+            placeholder_uri: Some("ar://placeholder".to_string()),
+        })
+    );
+
+    assert_eq!(decode_content_hash(&encode_content_hash(&[0xab; 32])).unwrap(), [0xab; 32]);
+    assert!(decode_content_hash("ab").is_err());
+    assert_eq!(decode_accent_color("#123456").unwrap(), [0x12, 0x34, 0x56]);
+    assert!(decode_accent_color("#12345").is_err());
+
+    let mut duplicated = gallery_json.clone();
+    duplicated.seats.push(duplicated.seats[0].clone());
+    assert!(duplicated.records().is_err());
+}
+
+#[test]
+fn test_gallery_json_validates_every_seat() {
+    let seat = |hero_id: u8| NFTRecordJson::from(&NFTRecord {
+        hero_id,
+        content_uri: "ipfs://hero".to_string(),
+        key_nft: Pubkey::new_unique(),
+        last_price: 0,
+        listed_price: 1,
+        sale_count: 0,
+        moderation: ModerationStatus::Visible,
+        content_hash: None,
+        caption: String::new(),
+        link: String::new(),
+        accent_color: None,
+        last_sold_at: 0,
+        placeholder_uri: String::new(),
+    });
+    let gallery_json = GalleryJson {
+        repository: Pubkey::new_unique().to_string(),
+        nft_count: NFT_COUNT,
+        nft_record_size: NFT_RECORD_SIZE,
+        config: None,
+        seats: (0..5).map(seat).collect(),
+    };
+    assert_eq!(gallery_json.records().unwrap().len(), 5);
+
+    // the last seat is refused, whatever its fault
+    let first_nft = gallery_json.seats[0].key_nft.clone();
+    let faults: Vec<Box<dyn Fn(&mut NFTRecordJson)>> = vec![
+        Box::new(|seat: &mut NFTRecordJson| seat.content_uri = "http://hero".to_string()),
+        Box::new(|seat: &mut NFTRecordJson| seat.content_uri = format!("ipfs://{}", "a".repeat(MAX_CONTENT_URI_LENGTH))),
+        Box::new(|seat: &mut NFTRecordJson| seat.placeholder_uri = "http://placeholder".to_string()),
+        Box::new(|seat: &mut NFTRecordJson| seat.moderation = ModerationStatus::Hidden),
+        Box::new(|seat: &mut NFTRecordJson| seat.caption = "c".repeat(MAX_CAPTION_LENGTH + 1)),
+        Box::new(|seat: &mut NFTRecordJson| seat.link = "http://example.com".to_string()),
+        Box::new(|seat: &mut NFTRecordJson| seat.link = format!("https://{}", "a".repeat(MAX_LINK_LENGTH))),
+        Box::new(move |seat: &mut NFTRecordJson| seat.key_nft = first_nft.clone()),
+    ];
+    for fault in faults.iter() {
+        let mut refused = gallery_json.clone();
+        fault(&mut refused.seats[4]);
+        assert!(refused.records().unwrap_err().to_string().contains("hero 4"));
+    }
+    // the file's config replaces the default prefixes
+    let mut refused = gallery_json.clone();
+    refused.config = Some(GalleryConfig {
+        allowed_uri_prefixes: vec!["ar://".to_string()],
+        ..GalleryConfig::default()
+    });
+    assert!(refused.records().unwrap_err().to_string().contains("hero 0"));
+
+    // two seats per transaction
+    let transactions = gallery_json
+        .import_transactions(&Pubkey::new_unique(), &Pubkey::new_unique(), false, |instructions| {
+            instructions[0].accounts.len() <= 4
+        })
+        .unwrap();
+    let hero_ids: Vec<Vec<u8>> = transactions
+        .iter()
+        .map(|instructions| match HeroInstruction::unpack(&instructions[0].data).unwrap() {
+            HeroInstruction::AddRecords(args) => args.iter().map(|record_args| record_args.hero_id).collect(),
+            instruction => panic!("unexpected {:?}", instruction),
+        })
+        .collect();
+    assert_eq!(hero_ids, vec![vec![0, 1], vec![2, 3], vec![4]]);
+}
+
+#[test]
+fn test_history_keeps_last_entries() {
+    let mut data = vec![0; HISTORY_SIZE];
+    let start = 3 * SEAT_HISTORY_SIZE;
+    let seat_data = &mut data[start..start + SEAT_HISTORY_SIZE];
+    for price in 0..HISTORY_LENGTH as u64 + 2 {
+        let mut seat_history = SeatHistory::unpack(seat_data).unwrap();
+        seat_history.push(ProvenanceEntry {
+            kind: ProvenanceKind::Sale,
+            seller: Pubkey::new_unique(),
+            buyer: Pubkey::new_unique(),
+            price,
+            slot: price,
+            timestamp: 0,
+        });
+        seat_history.pack(seat_data).unwrap();
+    }
+
+    let history = History::unpack(&data).unwrap();
+    let prices: Vec<u64> = history
+        .seat(3)
+        .unwrap()
+        .entries
+        .iter()
+        .map(|entry| entry.price)
+        .collect();
+    assert_eq!(prices, (2..HISTORY_LENGTH as u64 + 2).collect::<Vec<_>>());
+    assert!(history.seat(0).unwrap().entries.is_empty());
+}
+
+#[test]
+fn test_graveyard_lists_every_page() {
+    // five burials in pages of two
+    let index = GraveyardIndex { buried: 5, page_capacity: 2 };
+    let data = index.try_to_vec().unwrap();
+    assert_eq!(index.page_count(), 3);
+    let mut pages = vec![vec![0; index.page_size()]; 3];
+    for hero_id in 0..5u8 {
+        let (page, slot) = index.locate(hero_id as u32);
+        let start = slot * DEAD_NFT_SIZE;
+        DeadNFT {
+            mint: Pubkey::new_unique(),
+            hero_id,
+            former_owner: Pubkey::new_unique(),
+            sale_price: 1,
+            died_at: 0,
+        }
+        .serialize(&mut &mut pages[page as usize][start..start + DEAD_NFT_SIZE])
+        .unwrap();
+    }
+
+    let graveyard = Graveyard::unpack(&data, &pages).unwrap();
+    assert_eq!(graveyard.index, index);
+    let hero_ids: Vec<u8> = graveyard.dead.iter().map(|dead_nft| dead_nft.hero_id).collect();
+    assert_eq!(hero_ids, vec![0, 1, 2, 3, 4]);
+
+    // a missing page, or a page of another size
+    assert!(Graveyard::unpack(&data, &pages[..2]).is_err());
+    pages[2].push(0);
+    assert!(Graveyard::unpack(&data, &pages).is_err());
+    let empty = GraveyardIndex { buried: 0, page_capacity: 0 };
+    assert!(Graveyard::unpack_index(&empty.try_to_vec().unwrap()).is_err());
+}
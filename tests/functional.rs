@@ -0,0 +1,128 @@
+use {
+    borsh::BorshDeserialize,
+    hall_of_heros::{
+        instruction::{create_graveyard, create_history, migrate_repository},
+        processor::{CreateGraveyardArgs, Processor},
+        state::{
+            find_graveyard_address, find_history_address, find_migration_address, GraveyardIndex,
+            GRAVEYARD_SIZE, HISTORY_SIZE, LEGACY_NFT_RECORD_SIZE, NFT_COUNT, REPOSITORY_SIZE,
+            REPO_ACCOUNT_SEED,
+        },
+    },
+    solana_program::{pubkey::Pubkey, system_program},
+    solana_program_test::{processor, BanksClient, ProgramTest},
+    solana_sdk::{
+        account::Account,
+        instruction::Instruction,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    },
+};
+
+// program test holding the repository of a funded admin
+fn program_test(program_id: &Pubkey, admin: &Keypair, repository_data: Vec<u8>) -> (ProgramTest, Pubkey) {
+    let mut program_test = ProgramTest::new("hall_of_heros", *program_id, processor!(Processor::process));
+    let repository = Pubkey::create_with_seed(&admin.pubkey(), REPO_ACCOUNT_SEED, program_id).unwrap();
+    program_test.add_account(admin.pubkey(), Account::new(1_000_000_000, 0, &system_program::id()));
+    program_test.add_account(
+        repository,
+        Account {
+            lamports: 1_000_000_000,
+            data: repository_data,
+            owner: *program_id,
+            ..Account::default()
+        },
+    );
+    (program_test, repository)
+}
+
+// anyone can send lamports to a program derived address before the gallery creates it
+fn pre_fund(program_test: &mut ProgramTest, address: &Pubkey) {
+    program_test.add_account(*address, Account::new(1, 0, &system_program::id()));
+}
+
+async fn process(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    admin: &Keypair,
+    instructions: &[Instruction],
+) {
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    transaction.sign(&[payer, admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+// created program owned, rent exempt and of the given size
+async fn assert_created(banks_client: &mut BanksClient, address: &Pubkey, program_id: &Pubkey, size: usize) {
+    let rent = banks_client.get_rent().await.unwrap();
+    let account = banks_client.get_account(*address).await.unwrap().unwrap();
+    assert_eq!(account.owner, *program_id);
+    assert_eq!(account.data.len(), size);
+    assert_eq!(account.lamports, rent.minimum_balance(size));
+}
+
+#[tokio::test]
+async fn test_create_history_pre_funded() {
+    let program_id = Pubkey::new_unique();
+    let admin = Keypair::new();
+    let (mut program_test, repository) = program_test(&program_id, &admin, vec![0; REPOSITORY_SIZE]);
+    let (history, _) = find_history_address(&repository, &program_id);
+    pre_fund(&mut program_test, &history);
+    let (mut banks_client, payer, _) = program_test.start().await;
+
+    process(
+        &mut banks_client,
+        &payer,
+        &admin,
+        &[create_history(&program_id, &admin.pubkey(), &repository)],
+    )
+    .await;
+    assert_created(&mut banks_client, &history, &program_id, HISTORY_SIZE).await;
+}
+
+#[tokio::test]
+async fn test_create_graveyard_pre_funded() {
+    let program_id = Pubkey::new_unique();
+    let admin = Keypair::new();
+    let (mut program_test, repository) = program_test(&program_id, &admin, vec![0; REPOSITORY_SIZE]);
+    let (graveyard, _) = find_graveyard_address(&repository, &program_id);
+    pre_fund(&mut program_test, &graveyard);
+    let (mut banks_client, payer, _) = program_test.start().await;
+
+    process(
+        &mut banks_client,
+        &payer,
+        &admin,
+        &[create_graveyard(&program_id, &admin.pubkey(), &repository, CreateGraveyardArgs { page_capacity: 4 })],
+    )
+    .await;
+    assert_created(&mut banks_client, &graveyard, &program_id, GRAVEYARD_SIZE).await;
+    let data = banks_client.get_account(graveyard).await.unwrap().unwrap().data;
+    assert_eq!(
+        GraveyardIndex::try_from_slice(&data).unwrap(),
+        GraveyardIndex { buried: 0, page_capacity: 4 }
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_repository_pre_funded() {
+    let program_id = Pubkey::new_unique();
+    let admin = Keypair::new();
+    let (mut program_test, repository) =
+        program_test(&program_id, &admin, vec![0; NFT_COUNT * LEGACY_NFT_RECORD_SIZE]);
+    let (migration, _) = find_migration_address(&repository, &program_id);
+    pre_fund(&mut program_test, &migration);
+    let (mut banks_client, payer, _) = program_test.start().await;
+
+    process(
+        &mut banks_client,
+        &payer,
+        &admin,
+        &[migrate_repository(&program_id, &admin.pubkey(), &repository, true)],
+    )
+    .await;
+    assert_created(&mut banks_client, &migration, &program_id, REPOSITORY_SIZE).await;
+    // the legacy repository is closed, to be recreated with the current layout
+    assert_eq!(banks_client.get_account(repository).await.unwrap(), None);
+}
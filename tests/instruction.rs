@@ -0,0 +1,261 @@
+use {
+    hall_of_heros::{
+        instruction::{buy_record, buy_records, update_record, BuySeatAccounts, HeroInstruction},
+        processor::{
+            AddRecordArgs, BuyRecordArgs, BuyRecordV2Args, BuySeatV2Args, CreateGraveyardArgs,
+            ModerateContentArgs, RemoveRecordArgs, SwapSeatsArgs, TransferSeatArgs, UpdateConfigArgs,
+            UpdateContentArgs, UpdatePriceArgs, UpdateRecordArgs,
+        },
+        state::{
+            find_gallery_authority_address, find_graveyard_address, find_graveyard_page_address,
+            find_history_address, ModerationStatus,
+        },
+    },
+    solana_program::{instruction::AccountMeta, pubkey::Pubkey, system_program, sysvar},
+};
+
+#[test]
+fn test_pack_unpack_round_trip() {
+    let instructions = vec![
+        HeroInstruction::AddRecord(AddRecordArgs {
+            hero_id: 3,
+            content_uri: "https://arweave.net/hero".to_string(),
+            key_nft: Pubkey::new_unique(),
+            last_price: 1,
+            listed_price: 2,
+            force: true,
+            content_hash: Some([7; 32]),
+            caption: "First of the hall".to_string(),
+            link: "https://example.com/hero".to_string(),
+            accent_color: Some([0xff, 0x88, 0x00]),
+        }),
+        HeroInstruction::UpdateRecord(UpdateRecordArgs {
+            hero_id: 3,
+            key_nft: Pubkey::new_unique(),
+            new_price: 5,
+            content_uri: "https://arweave.net/new".to_string(),
+            content_hash: None,
+        }),
+        HeroInstruction::BuyRecord(BuyRecordArgs {
+            hero_id: 3,
+            dead_uri: "https://arweave.net/dead".to_string(),
+            dead_name: "Dead Hero".to_string(),
+        }),
+        HeroInstruction::OnChainMinting,
+        HeroInstruction::RemoveRecord(RemoveRecordArgs { hero_id: 3 }),
+        HeroInstruction::AddRecords(vec![AddRecordArgs {
+            hero_id: 4,
+            content_uri: "ar://hero".to_string(),
+            key_nft: Pubkey::new_unique(),
+            last_price: 0,
+            listed_price: 7,
+            force: false,
+            content_hash: None,
+            caption: String::new(),
+            link: String::new(),
+            accent_color: None,
+        }]),
+        HeroInstruction::SwapSeats(SwapSeatsArgs {
+            hero_id_a: 1,
+            hero_id_b: 2,
+            lamports_a_to_b: 0,
+            lamports_b_to_a: 10,
+        }),
+        HeroInstruction::TransferSeat(TransferSeatArgs {
+            hero_id: 1,
+            new_listed_price: Some(0),
+        }),
+        HeroInstruction::CreateHistory,
+        HeroInstruction::UpdateConfig(UpdateConfigArgs {
+            dead_name_template: "Fallen Hero #{hero_id} ({sale_count})".to_string(),
+            dead_uri_base: "https://arweave.net/dead/".to_string(),
+            allowed_uri_prefixes: vec!["ar://".to_string(), "ipfs://".to_string()],
+            cooldown_seconds: 3600,
+        }),
+        HeroInstruction::BuyRecordV2(BuyRecordV2Args { hero_id: 3 }),
+        HeroInstruction::BuyRecordsV2(vec![BuySeatV2Args {
+            hero_id: 5,
+            max_price: 100,
+        }]),
+        HeroInstruction::ModerateContent(ModerateContentArgs {
+            hero_id: 2,
+            moderation: ModerationStatus::Hidden,
+            placeholder_uri: Some("https://arweave.net/placeholder".to_string()),
+        }),
+        HeroInstruction::UpdateContent(UpdateContentArgs {
+            hero_id: 2,
+            key_nft: Pubkey::new_unique(),
+            content_uri: Some("ar://hero".to_string()),
+            content_hash: Some(None),
+            caption: Some("Hero".to_string()),
+            link: None,
+            accent_color: Some(Some([0, 0, 0])),
+        }),
+        HeroInstruction::UpdatePrice(UpdatePriceArgs {
+            hero_id: 2,
+            key_nft: Pubkey::new_unique(),
+            new_price: 9,
+        }),
+        HeroInstruction::CreateGraveyard(CreateGraveyardArgs { page_capacity: 100 }),
+        HeroInstruction::MigrateRepository,
+    ];
+    for instruction in instructions {
+        let packed = instruction.pack();
+        assert_eq!(HeroInstruction::unpack(&packed).unwrap(), instruction);
+    }
+    // Withdraw was removed, tag 7 is unused
+    assert!(HeroInstruction::unpack(&[5, 42, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    assert!(HeroInstruction::unpack(&[7, 0, 0, 0, 0]).is_err());
+}
+
+#[test]
+fn test_update_record_sync_metadata_accounts() {
+    let program_id = Pubkey::new_unique();
+    let setter = Pubkey::new_unique();
+    let repository = Pubkey::new_unique();
+    let nft_token_account = Pubkey::new_unique();
+    let args = UpdateRecordArgs {
+        hero_id: 1,
+        key_nft: Pubkey::new_unique(),
+        new_price: 5,
+        content_uri: "https://arweave.net/new".to_string(),
+        content_hash: None,
+    };
+
+    let ix = update_record(&program_id, &setter, &repository, &nft_token_account, false, args.clone());
+    assert_eq!(ix.accounts.len(), 4);
+
+    let ix = update_record(&program_id, &setter, &repository, &nft_token_account, true, args);
+    assert_eq!(ix.accounts.len(), 7);
+    assert!(ix.accounts[4].is_writable);
+    assert_eq!(ix.accounts[5].pubkey, spl_token_metadata::id());
+    assert_eq!(
+        ix.accounts[6].pubkey,
+        find_gallery_authority_address(&repository, &program_id).0
+    );
+}
+
+fn buy_seat_accounts() -> BuySeatAccounts {
+    BuySeatAccounts {
+        prev_owner: Pubkey::new_unique(),
+        old_nft_mint: Pubkey::new_unique(),
+        old_nft_token_account: Pubkey::new_unique(),
+        old_nft_metadata: Pubkey::new_unique(),
+        new_nft_mint: Pubkey::new_unique(),
+        nft_token_account_to_send: Pubkey::new_unique(),
+        nft_token_account_to_receive: Pubkey::new_unique(),
+        graveyard_page: Pubkey::new_unique(),
+    }
+}
+
+#[test]
+fn test_buy_record_accounts() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let repository = Pubkey::new_unique();
+    let seat = buy_seat_accounts();
+    let ix = |create_receive_account| {
+        buy_record(
+            &program_id,
+            &admin,
+            &buyer,
+            &seat.prev_owner,
+            &repository,
+            &seat.old_nft_mint,
+            &seat.old_nft_token_account,
+            &seat.old_nft_metadata,
+            &seat.new_nft_mint,
+            &seat.nft_token_account_to_send,
+            &seat.nft_token_account_to_receive,
+            &seat.graveyard_page,
+            create_receive_account,
+            BuyRecordV2Args { hero_id: 2 },
+        )
+    };
+
+    // in the order process_buy_record reads them
+    let mut accounts = vec![
+        AccountMeta::new_readonly(admin, true),
+        AccountMeta::new(buyer, true),
+        AccountMeta::new(seat.prev_owner, false),
+        AccountMeta::new(repository, false),
+        AccountMeta::new_readonly(seat.old_nft_mint, false),
+        AccountMeta::new_readonly(seat.old_nft_token_account, false),
+        AccountMeta::new(seat.old_nft_metadata, false),
+        AccountMeta::new_readonly(seat.new_nft_mint, false),
+        AccountMeta::new(seat.nft_token_account_to_send, false),
+        AccountMeta::new(seat.nft_token_account_to_receive, false),
+        AccountMeta::new(seat.graveyard_page, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_token_metadata::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(find_history_address(&repository, &program_id).0, false),
+        AccountMeta::new(find_graveyard_address(&repository, &program_id).0, false),
+        AccountMeta::new_readonly(find_gallery_authority_address(&repository, &program_id).0, false),
+    ];
+    assert_eq!(ix(false).accounts, accounts);
+
+    accounts.push(AccountMeta::new_readonly(spl_associated_token_account::id(), false));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    let ix = ix(true);
+    assert_eq!(ix.accounts, accounts);
+    assert_eq!(
+        HeroInstruction::unpack(&ix.data).unwrap(),
+        HeroInstruction::BuyRecordV2(BuyRecordV2Args { hero_id: 2 })
+    );
+}
+
+#[test]
+fn test_buy_records_accounts() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let repository = Pubkey::new_unique();
+    // the second seat starts the next graveyard page
+    let (next_page, _) = find_graveyard_page_address(&repository, 1, &program_id);
+    let seats = vec![
+        buy_seat_accounts(),
+        BuySeatAccounts { graveyard_page: next_page, ..buy_seat_accounts() },
+    ];
+    let args = vec![
+        BuySeatV2Args { hero_id: 3, max_price: 10 },
+        BuySeatV2Args { hero_id: 5, max_price: 20 },
+    ];
+    let ix = buy_records(
+        &program_id,
+        &admin,
+        &buyer,
+        &repository,
+        seats.iter().copied().zip(args.iter().cloned()).collect(),
+    );
+
+    // in the order process_buy_records reads them
+    let mut accounts = vec![
+        AccountMeta::new_readonly(admin, true),
+        AccountMeta::new(buyer, true),
+        AccountMeta::new(repository, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_token_metadata::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new(find_history_address(&repository, &program_id).0, false),
+        AccountMeta::new(find_graveyard_address(&repository, &program_id).0, false),
+        AccountMeta::new_readonly(find_gallery_authority_address(&repository, &program_id).0, false),
+    ];
+    for seat in seats.iter() {
+        accounts.extend(vec![
+            AccountMeta::new(seat.prev_owner, false),
+            AccountMeta::new_readonly(seat.old_nft_mint, false),
+            AccountMeta::new_readonly(seat.old_nft_token_account, false),
+            AccountMeta::new(seat.old_nft_metadata, false),
+            AccountMeta::new_readonly(seat.new_nft_mint, false),
+            AccountMeta::new(seat.nft_token_account_to_send, false),
+            AccountMeta::new(seat.nft_token_account_to_receive, false),
+            AccountMeta::new(seat.graveyard_page, false),
+        ]);
+    }
+    assert_eq!(ix.accounts, accounts);
+    assert_eq!(HeroInstruction::unpack(&ix.data).unwrap(), HeroInstruction::BuyRecordsV2(args));
+}
@@ -0,0 +1,82 @@
+use {
+    borsh::BorshSerialize,
+    hall_of_heros::state::{
+        GalleryConfig, LegacyNFTRecord, ModerationStatus, NFTRecord, MAX_CAPTION_LENGTH, MAX_CONTENT_URI_LENGTH,
+        MAX_LINK_LENGTH, NFT_RECORD_SIZE, REPOSITORY_SIZE,
+    },
+    solana_program::{entrypoint::MAX_PERMITTED_DATA_INCREASE, pubkey::Pubkey},
+};
+
+#[test]
+fn test_gallery_config_allowed_uri() {
+    let mut config = GalleryConfig::default();
+    assert!(config.is_allowed_uri("https://example.com/hero.png"));
+    assert!(config.is_allowed_uri("ar://hero"));
+    assert!(config.is_allowed_uri("ipfs://hero"));
+    assert!(!config.is_allowed_uri("javascript:alert(1)"));
+    assert!(!config.is_allowed_uri("data:text/html,hero"));
+    assert!(!config.is_allowed_uri("http://example.com/hero.png"));
+    assert!(!config.is_allowed_uri("ar://hero\n"));
+
+    config.allowed_uri_prefixes = vec!["ar://".to_string(), "https://arweave.net/".to_string()];
+    assert!(config.is_allowed_uri("ar://hero"));
+    assert!(config.is_allowed_uri("https://arweave.net/hero"));
+    assert!(config.is_allowed_uri(""));
+    assert!(!config.is_allowed_uri("javascript:alert(1)"));
+    assert!(!config.is_allowed_uri("https://example.com/hero.png"));
+    assert!(!config.is_allowed_uri("ar://hero\u{7}"));
+}
+
+#[test]
+fn test_gallery_config_cooldown() {
+    let mut config = GalleryConfig::default();
+    assert_eq!(config.cooldown_ends_at(1_000), 1_000);
+
+    config.cooldown_seconds = 60;
+    assert_eq!(config.cooldown_ends_at(0), 0);
+    assert_eq!(config.cooldown_ends_at(1_000), 1_060);
+
+    config.cooldown_seconds = u64::MAX;
+    assert_eq!(config.cooldown_ends_at(1_000), i64::MAX);
+}
+
+#[test]
+fn test_nft_record_display_uri() {
+    let mut nft_record = NFTRecord::from(LegacyNFTRecord {
+        hero_id: 0,
+        content_uri: "ar://hero".to_string(),
+        key_nft: Pubkey::new_unique(),
+        last_price: 0,
+        listed_price: 0,
+    });
+    assert_eq!(nft_record.display_uri(), "ar://hero");
+
+    nft_record.moderation = ModerationStatus::Hidden;
+    assert_eq!(nft_record.display_uri(), "ar://hero");
+    nft_record.placeholder_uri = "ar://placeholder".to_string();
+    assert_eq!(nft_record.display_uri(), "ar://placeholder");
+
+    nft_record.moderation = ModerationStatus::Visible;
+    assert_eq!(nft_record.display_uri(), "ar://hero");
+}
+
+#[test]
+fn test_largest_nft_record_fits() {
+    let nft_record = NFTRecord {
+        hero_id: 11,
+        content_uri: "a".repeat(MAX_CONTENT_URI_LENGTH),
+        key_nft: Pubkey::new_unique(),
+        last_price: u64::MAX,
+        listed_price: u64::MAX,
+        sale_count: u32::MAX,
+        moderation: ModerationStatus::Hidden,
+        content_hash: Some([0xff; 32]),
+        caption: "c".repeat(MAX_CAPTION_LENGTH),
+        link: "l".repeat(MAX_LINK_LENGTH),
+        accent_color: Some([0xff; 3]),
+        last_sold_at: i64::MAX,
+        placeholder_uri: "p".repeat(MAX_CONTENT_URI_LENGTH),
+    };
+    assert!(nft_record.try_to_vec().unwrap().len() <= NFT_RECORD_SIZE);
+    assert!(REPOSITORY_SIZE <= MAX_PERMITTED_DATA_INCREASE);
+}